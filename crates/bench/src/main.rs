@@ -3,7 +3,8 @@ use core_crnn::thinking_layer::ThinkingLayer;
 use std::time::Instant;
 
 fn main() {
-    let mut model = ThinkingLayer::new(8, 256, 8, ActivationFunction::Other(|x| x)).unwrap();
+    let mut model: ThinkingLayer =
+        ThinkingLayer::new(8, 256, 8, ActivationFunction::Identity).unwrap();
 
     let now = Instant::now();
 
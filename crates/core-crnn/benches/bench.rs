@@ -1,22 +1,98 @@
 use core_crnn::activation_function::ActivationFunction;
 use core_crnn::thinking_layer::ThinkingLayer;
+use core_crnn::thinking_layer_batch::ThinkingLayerBatch;
 use criterion::{criterion_group, criterion_main, Criterion};
 
+/// Tick as computed before the weights were stored as a matrix, for comparison with "large":
+/// every neuron walks its part of the genome and skips its self-connection.
+fn per_neuron_tick(
+    layer: &ThinkingLayer,
+    genome: &[f64],
+    states: &mut [f64],
+    input: &[f64],
+    internal_tick: usize,
+) {
+    states[..input.len()].copy_from_slice(input);
+    let previous = states.to_vec();
+    let internal_size = layer.internal_size();
+    let neuron_data_length = 2 + internal_size - 1;
+
+    for (neuron, state) in states.iter_mut().enumerate().skip(input.len()) {
+        let genes = &genome[neuron * neuron_data_length..(neuron + 1) * neuron_data_length];
+        if !internal_tick.is_multiple_of(genes[1].round().max(1.0) as usize) {
+            continue;
+        }
+
+        let sources = (0..internal_size).filter(|source| *source != neuron);
+        let sum: f64 = genes[2..]
+            .iter()
+            .zip(sources)
+            .map(|(weight, source)| weight * previous[source])
+            .sum();
+        *state = sum + genes[0];
+    }
+}
+
 pub fn criterion_benchmark(c: &mut Criterion) {
-    let mut small_model = ThinkingLayer::new(4, 32, 8, ActivationFunction::Other(|x| x)).unwrap();
-    let mut medium_model = ThinkingLayer::new(4, 256, 8, ActivationFunction::Other(|x| x)).unwrap();
-    let mut large_model = ThinkingLayer::new(4, 2048, 8, ActivationFunction::Other(|x| x)).unwrap();
+    let mut small_model = ThinkingLayer::new(4, 32, 8, ActivationFunction::Identity).unwrap();
+    let mut medium_model = ThinkingLayer::new(4, 256, 8, ActivationFunction::Identity).unwrap();
+    let mut large_model = ThinkingLayer::new(4, 2048, 8, ActivationFunction::Identity).unwrap();
+    let mut large_model_f32 = large_model.convert::<f32>();
+    let mut medium_layers: Vec<ThinkingLayer> = (0..64)
+        .map(|_| ThinkingLayer::new(4, 256, 8, ActivationFunction::Identity).unwrap())
+        .collect();
+    let mut medium_batch = ThinkingLayerBatch::from_layers(&medium_layers).unwrap();
+    let mut medium_samples = ThinkingLayerBatch::with_samples(&[medium_model.clone()], 64).unwrap();
+    let batch_input = vec![0.; 64 * 4];
 
     c.bench_function("small", |b| {
-        b.iter(|| small_model.tick(Some(vec![0., 0., 0., 0.])))
+        b.iter(|| small_model.tick(Some(&[0., 0., 0., 0.])))
     });
 
     c.bench_function("medium", |b| {
-        b.iter(|| medium_model.tick(Some(vec![0., 0., 0., 0.])))
+        b.iter(|| medium_model.tick(Some(&[0., 0., 0., 0.])))
     });
 
     c.bench_function("large", |b| {
-        b.iter(|| large_model.tick(Some(vec![0., 0., 0., 0.])))
+        b.iter(|| large_model.tick(Some(&[0., 0., 0., 0.])))
+    });
+
+    let large_genome = large_model.genome();
+    let mut large_states = large_model.neuron_states().to_vec();
+    let mut large_tick = 0;
+    c.bench_function("large_per_neuron", |b| {
+        b.iter(|| {
+            large_tick += 1;
+            per_neuron_tick(
+                &large_model,
+                &large_genome,
+                &mut large_states,
+                &[0., 0., 0., 0.],
+                large_tick,
+            )
+        })
+    });
+
+    c.bench_function("large_f32", |b| {
+        b.iter(|| large_model_f32.tick(Some(&[0., 0., 0., 0.])))
+    });
+
+    // The same 64 layers ticked one by one, the baseline of the batches below.
+    c.bench_function("medium_layers_64", |b| {
+        b.iter(|| {
+            for layer in &mut medium_layers {
+                layer.tick(Some(&[0., 0., 0., 0.]))
+            }
+        })
+    });
+
+    c.bench_function("medium_batch_64", |b| {
+        b.iter(|| medium_batch.tick(Some(&batch_input)))
+    });
+
+    // One layer played 64 times, the samples share a single copy of the weights.
+    c.bench_function("medium_samples_64", |b| {
+        b.iter(|| medium_samples.tick(Some(&batch_input)))
     });
 }
 
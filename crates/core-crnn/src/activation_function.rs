@@ -1,24 +1,253 @@
-#[derive(Clone, Debug)]
+use crate::float::Float;
+use anyhow::anyhow;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::sync::{LazyLock, RwLock};
+
+type Registry = RwLock<HashMap<String, (fn(f64) -> f64, Option<fn(f64) -> f64>)>>;
+
+/// Custom activation functions by name, consulted when a model file refers to one.
+static REGISTRY: LazyLock<Registry> = LazyLock::new(Default::default);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum ActivationFunction {
     Tanh,
     Sigmoid,
     Relu,
+    /// Relu passing negative values scaled by `slope`.
+    LeakyRelu {
+        slope: f64,
+    },
+    /// `alpha * (e^x - 1)` for negative values, identity otherwise.
+    Elu {
+        alpha: f64,
+    },
+    /// Gaussian error linear unit, using the common tanh approximation.
+    Gelu,
+    /// `x / (1 + |x|)`
+    Softsign,
+    Sine,
+    /// `e^(-x^2)`
+    Gaussian,
+    /// One for values of at least zero, zero otherwise.
+    Step,
+    Identity,
+    /// Function registered under a name, persisted by that name.
+    Custom(CustomActivationFunction),
+    /// Arbitrary function pointer, cannot be persisted. Prefer [`ActivationFunction::Custom`].
+    #[serde(skip)]
     Other(fn(f64) -> f64),
 }
 
+/// A function registered with [`register_activation_function`].
+///
+/// Serializes to its name only, deserializing looks the name up in the registry.
+#[derive(Clone)]
+pub struct CustomActivationFunction {
+    name: String,
+    function: fn(f64) -> f64,
+    derivative: Option<fn(f64) -> f64>,
+}
+
+/// Registers `function` under `name` so models using it can be saved and loaded again.
+///
+/// Registering a name again replaces the previous function. Its derivative is approximated
+/// numerically, see [`register_activation_function_with_derivative`] to supply one.
+pub fn register_activation_function(
+    name: impl Into<String>,
+    function: fn(f64) -> f64,
+) -> ActivationFunction {
+    register(name.into(), function, None)
+}
+
+/// Like [`register_activation_function`], with the exact derivative of `function`.
+pub fn register_activation_function_with_derivative(
+    name: impl Into<String>,
+    function: fn(f64) -> f64,
+    derivative: fn(f64) -> f64,
+) -> ActivationFunction {
+    register(name.into(), function, Some(derivative))
+}
+
+fn register(
+    name: String,
+    function: fn(f64) -> f64,
+    derivative: Option<fn(f64) -> f64>,
+) -> ActivationFunction {
+    REGISTRY
+        .write()
+        .unwrap()
+        .insert(name.clone(), (function, derivative));
+    ActivationFunction::Custom(CustomActivationFunction {
+        name,
+        function,
+        derivative,
+    })
+}
+
 impl ActivationFunction {
-    pub fn apply(&self, x: f64) -> f64 {
+    /// Looks up a function registered with [`register_activation_function`].
+    pub fn custom(name: &str) -> anyhow::Result<Self> {
+        CustomActivationFunction::lookup(name).map(ActivationFunction::Custom)
+    }
+
+    pub fn apply<F: Float>(&self, x: F) -> F {
         match self {
             ActivationFunction::Tanh => x.tanh(),
-            ActivationFunction::Sigmoid => 1.0 / (1.0 + (-x).exp()),
+            ActivationFunction::Sigmoid => F::one() / (F::one() + (-x).exp()),
             ActivationFunction::Relu => {
-                if x < 0.0 {
-                    0.0
+                if x < F::zero() {
+                    F::zero()
+                } else {
+                    x
+                }
+            }
+            ActivationFunction::LeakyRelu { slope } => {
+                if x < F::zero() {
+                    x * F::from_f64(*slope)
+                } else {
+                    x
+                }
+            }
+            ActivationFunction::Elu { alpha } => {
+                if x < F::zero() {
+                    F::from_f64(*alpha) * x.exp_m1()
                 } else {
                     x
                 }
             }
-            ActivationFunction::Other(function) => function(x),
+            ActivationFunction::Gelu => {
+                let half = F::from_f64(0.5);
+                let inner = F::from_f64(std::f64::consts::FRAC_2_PI.sqrt())
+                    * (x + F::from_f64(0.044715) * x.powi(3));
+                half * x * (F::one() + inner.tanh())
+            }
+            ActivationFunction::Softsign => x / (F::one() + x.abs()),
+            ActivationFunction::Sine => x.sin(),
+            ActivationFunction::Gaussian => (-x * x).exp(),
+            ActivationFunction::Step => {
+                if x < F::zero() {
+                    F::zero()
+                } else {
+                    F::one()
+                }
+            }
+            ActivationFunction::Identity => x,
+            ActivationFunction::Custom(custom) => F::from_f64((custom.function)(x.into_f64())),
+            ActivationFunction::Other(function) => F::from_f64(function(x.into_f64())),
+        }
+    }
+
+    /// Derivative of [`ActivationFunction::apply`] at `x`.
+    ///
+    /// Custom functions without a supplied derivative and [`ActivationFunction::Other`] are
+    /// differentiated numerically.
+    pub fn derivative<F: Float>(&self, x: F) -> F {
+        match self {
+            ActivationFunction::Tanh => F::one() - x.tanh().powi(2),
+            ActivationFunction::Sigmoid => {
+                let sigmoid = self.apply(x);
+                sigmoid * (F::one() - sigmoid)
+            }
+            ActivationFunction::Relu | ActivationFunction::Step if x < F::zero() => F::zero(),
+            ActivationFunction::Relu => F::one(),
+            ActivationFunction::LeakyRelu { slope } if x < F::zero() => F::from_f64(*slope),
+            ActivationFunction::Elu { alpha } if x < F::zero() => F::from_f64(*alpha) * x.exp(),
+            ActivationFunction::LeakyRelu { .. } | ActivationFunction::Elu { .. } => F::one(),
+            ActivationFunction::Gelu => {
+                let scale = F::from_f64(std::f64::consts::FRAC_2_PI.sqrt());
+                let cubic = F::from_f64(0.044715);
+                let inner = (scale * (x + cubic * x.powi(3))).tanh();
+                let half = F::from_f64(0.5);
+                half * (F::one() + inner)
+                    + half
+                        * x
+                        * (F::one() - inner * inner)
+                        * scale
+                        * (F::one() + F::from_f64(3.0) * cubic * x * x)
+            }
+            ActivationFunction::Softsign => F::one() / (F::one() + x.abs()).powi(2),
+            ActivationFunction::Sine => x.cos(),
+            ActivationFunction::Gaussian => F::from_f64(-2.0) * x * (-x * x).exp(),
+            ActivationFunction::Step => F::zero(),
+            ActivationFunction::Identity => F::one(),
+            ActivationFunction::Custom(CustomActivationFunction {
+                derivative: Some(derivative),
+                ..
+            }) => F::from_f64(derivative(x.into_f64())),
+            ActivationFunction::Custom(CustomActivationFunction { function, .. })
+            | ActivationFunction::Other(function) => {
+                F::from_f64(numerical_derivative(*function, x.into_f64()))
+            }
+        }
+    }
+
+    pub fn is_persistable(&self) -> bool {
+        !matches!(self, ActivationFunction::Other(_))
+    }
+}
+
+impl PartialEq for ActivationFunction {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                ActivationFunction::LeakyRelu { slope: a },
+                ActivationFunction::LeakyRelu { slope: b },
+            ) => a == b,
+            (ActivationFunction::Elu { alpha: a }, ActivationFunction::Elu { alpha: b }) => a == b,
+            (ActivationFunction::Custom(a), ActivationFunction::Custom(b)) => a.name == b.name,
+            (ActivationFunction::Other(a), ActivationFunction::Other(b)) => {
+                std::ptr::fn_addr_eq(*a, *b)
+            }
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
         }
     }
 }
+
+impl CustomActivationFunction {
+    fn lookup(name: &str) -> anyhow::Result<Self> {
+        let (function, derivative) = *REGISTRY
+            .read()
+            .unwrap()
+            .get(name)
+            .ok_or_else(|| anyhow!("No activation function is registered as {:?}", name))?;
+
+        Ok(Self {
+            name: name.to_string(),
+            function,
+            derivative,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Central difference with a step relative to the magnitude of `x`.
+fn numerical_derivative(function: fn(f64) -> f64, x: f64) -> f64 {
+    let step = 1e-6 * x.abs().max(1.0);
+    (function(x + step) - function(x - step)) / (2.0 * step)
+}
+
+impl Debug for CustomActivationFunction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("CustomActivationFunction")
+            .field(&self.name)
+            .finish()
+    }
+}
+
+impl Serialize for CustomActivationFunction {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.name)
+    }
+}
+
+impl<'de> Deserialize<'de> for CustomActivationFunction {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Self::lookup(&name).map_err(serde::de::Error::custom)
+    }
+}
@@ -0,0 +1,384 @@
+use crate::float::Float;
+use crate::kernel;
+use crate::layer_settings::{Integrator, UpdateMode};
+use crate::thinking_layer::{blend, update_rate, write_input, ThinkingLayer};
+use anyhow::bail;
+use itertools::{izip, Either};
+
+/// One tick of a training sequence.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SequenceStep<F: Float = f64> {
+    /// Written into the input neurons before the tick, like [`ThinkingLayer::tick`].
+    pub input: Option<Vec<F>>,
+    /// Expected output after the tick, steps without one are not graded.
+    pub target: Option<Vec<F>>,
+}
+
+impl<F: Float> SequenceStep<F> {
+    pub fn new(input: Vec<F>, target: Vec<F>) -> Self {
+        Self {
+            input: Some(input),
+            target: Some(target),
+        }
+    }
+}
+
+/// How gradients are turned into parameter updates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Optimizer {
+    Sgd {
+        learning_rate: f64,
+    },
+    Adam {
+        learning_rate: f64,
+        beta1: f64,
+        beta2: f64,
+        epsilon: f64,
+    },
+}
+
+impl Optimizer {
+    pub fn sgd(learning_rate: f64) -> Self {
+        Optimizer::Sgd { learning_rate }
+    }
+
+    /// Adam with the usual defaults for everything but the learning rate.
+    pub fn adam(learning_rate: f64) -> Self {
+        Optimizer::Adam {
+            learning_rate,
+            beta1: 0.9,
+            beta2: 0.999,
+            epsilon: 1e-8,
+        }
+    }
+}
+
+/// Gradients of the mean squared error of a sequence with respect to the trainable parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradients<F: Float = f64> {
+    pub loss: f64,
+    /// Same layout as [`ThinkingLayer::weights`], self-connections of dense layers stay zero.
+    pub weights: Vec<F>,
+    pub biases: Vec<F>,
+}
+
+/// Supervised training of biases and weights by backpropagation through time.
+///
+/// Delays only decide which neurons update on which tick and how far. They are treated as a
+/// fixed mask and are not trained, neither are activation genes.
+#[derive(Debug, Clone)]
+pub struct BpttTrainer {
+    optimizer: Optimizer,
+    gradient_clip: Option<f64>,
+
+    /// Adam moment estimates, weights followed by biases.
+    first_moments: Vec<f64>,
+    second_moments: Vec<f64>,
+    steps: i32,
+}
+
+impl BpttTrainer {
+    pub fn new(optimizer: Optimizer) -> Self {
+        Self {
+            optimizer,
+            gradient_clip: None,
+            first_moments: Vec::new(),
+            second_moments: Vec::new(),
+            steps: 0,
+        }
+    }
+
+    /// Scales gradients down whenever their norm exceeds `max_norm`.
+    pub fn gradient_clip(mut self, max_norm: f64) -> Self {
+        self.gradient_clip = Some(max_norm);
+        self
+    }
+
+    /// Trains on every sequence once per epoch and returns the mean loss of the last epoch.
+    pub fn train<F: Float>(
+        &mut self,
+        layer: &mut ThinkingLayer<F>,
+        sequences: &[Vec<SequenceStep<F>>],
+        epochs: usize,
+    ) -> anyhow::Result<f64> {
+        let mut mean_loss = 0.0;
+
+        for _ in 0..epochs {
+            mean_loss = 0.0;
+            for sequence in sequences {
+                mean_loss += self.train_sequence(layer, sequence)?;
+            }
+            mean_loss /= sequences.len().max(1) as f64;
+        }
+
+        Ok(mean_loss)
+    }
+
+    /// Performs a single update on `sequence` and returns the loss before it.
+    ///
+    /// The sequence starts from the current neuron states and tick of `layer`, neither of which
+    /// is changed.
+    pub fn train_sequence<F: Float>(
+        &mut self,
+        layer: &mut ThinkingLayer<F>,
+        sequence: &[SequenceStep<F>],
+    ) -> anyhow::Result<f64> {
+        let mut gradients = Self::gradients(layer, sequence)?;
+
+        if let Some(max_norm) = self.gradient_clip {
+            let norm = gradients
+                .weights
+                .iter()
+                .chain(&gradients.biases)
+                .map(|gradient| gradient.into_f64().powi(2))
+                .sum::<f64>()
+                .sqrt();
+
+            if norm > max_norm {
+                let scale = F::from_f64(max_norm / norm);
+                gradients
+                    .weights
+                    .iter_mut()
+                    .chain(&mut gradients.biases)
+                    .for_each(|gradient| *gradient = *gradient * scale);
+            }
+        }
+
+        self.apply(layer, &gradients);
+        Ok(gradients.loss)
+    }
+
+    /// Unrolls `sequence` from the current state of `layer` and backpropagates the error.
+    pub fn gradients<F: Float>(
+        layer: &ThinkingLayer<F>,
+        sequence: &[SequenceStep<F>],
+    ) -> anyhow::Result<Gradients<F>> {
+        let internal_size = layer.internal_size();
+        let input_size = layer.input_size();
+        let output_start = internal_size - layer.output_size();
+        let row_length = layer.row_length();
+
+        if let UpdateMode::Continuous {
+            integrator: Integrator::RungeKutta4,
+            ..
+        } = layer.settings().update_mode
+        {
+            bail!("Backpropagation through time does not support Runge-Kutta integration")
+        }
+
+        if layer.settings().plasticity.is_plastic() {
+            bail!("Backpropagation through time does not support plastic weights")
+        }
+
+        if !layer.settings().output_readout.is_raw() {
+            bail!("Backpropagation through time only supports raw output read-outs")
+        }
+
+        for (index, step) in sequence.iter().enumerate() {
+            if step
+                .input
+                .as_ref()
+                .is_some_and(|input| input.len() != input_size)
+            {
+                bail!("Step {} has an input of the wrong length", index)
+            }
+            if step
+                .target
+                .as_ref()
+                .is_some_and(|target| target.len() != layer.output_size())
+            {
+                bail!("Step {} has a target of the wrong length", index)
+            }
+        }
+
+        let graded_values =
+            sequence.iter().filter(|step| step.target.is_some()).count() * layer.output_size();
+        let error_scale = F::from_f64(2.0 / graded_values.max(1) as f64);
+
+        // Forward pass, remembering the states every tick started from, the pre-activations and
+        // update rates of the neurons that were updated and the outputs.
+        let mut states = layer.neuron_states().to_vec();
+        let mut input_current = layer.input_current.clone();
+        let mut tick = layer.internal_tick().max(1);
+        let mut started_from = Vec::with_capacity(sequence.len());
+        let mut pre_activations = Vec::with_capacity(sequence.len());
+        let mut outputs = Vec::with_capacity(sequence.len());
+        let mut loss = 0.0;
+        let update_mode = layer.settings().update_mode;
+        let input_encoding = layer.settings().input_encoding;
+        let clamped_inputs = if input_encoding.is_clamped() {
+            input_size
+        } else {
+            0
+        };
+
+        for step in sequence {
+            if let Some(input) = &step.input {
+                write_input(
+                    input_encoding,
+                    input,
+                    &mut states[..input_size],
+                    &mut input_current,
+                );
+            }
+
+            let mut next_states = states.clone();
+            let mut sums = vec![None; internal_size];
+            for neuron_index in clamped_inputs..internal_size {
+                let Some(rate) = update_rate(update_mode, layer.delays()[neuron_index], tick)
+                else {
+                    continue;
+                };
+
+                let current = input_current
+                    .get(neuron_index)
+                    .copied()
+                    .unwrap_or(F::zero());
+                let sum =
+                    weighted_sum(layer, neuron_index, &states) + layer.bias(neuron_index) + current;
+                let activation = layer.neuron_activation_function(neuron_index).apply(sum);
+                next_states[neuron_index] = blend(states[neuron_index], activation, rate);
+                sums[neuron_index] = Some((sum, rate));
+            }
+
+            if let Some(target) = &step.target {
+                loss += next_states[output_start..]
+                    .iter()
+                    .zip(target)
+                    .map(|(output, target)| (*output - *target).into_f64().powi(2))
+                    .sum::<f64>();
+            }
+
+            started_from.push(states);
+            pre_activations.push(sums);
+            outputs.push(next_states[output_start..].to_vec());
+            states = next_states;
+            tick = tick.wrapping_add(1);
+        }
+
+        // Backward pass, `state_gradients` holds the gradient of the loss with respect to the
+        // states after the tick currently being processed.
+        let mut weight_gradients = vec![F::zero(); layer.weights().len()];
+        let mut bias_gradients = vec![F::zero(); internal_size];
+        let mut state_gradients = vec![F::zero(); internal_size];
+
+        for (step, states, sums, outputs) in
+            izip!(sequence, &started_from, &pre_activations, &outputs).rev()
+        {
+            if let Some(target) = &step.target {
+                for (gradient, output, target) in
+                    izip!(&mut state_gradients[output_start..], outputs, target)
+                {
+                    *gradient = *gradient + error_scale * (*output - *target);
+                }
+            }
+
+            let mut previous_gradients = vec![F::zero(); internal_size];
+            for neuron_index in 0..internal_size {
+                // Neurons pass the part of their state they keep straight through.
+                let (sum, rate) = sums[neuron_index].unwrap_or((F::zero(), F::zero()));
+                previous_gradients[neuron_index] = previous_gradients[neuron_index]
+                    + state_gradients[neuron_index] * (F::one() - rate);
+                if rate == F::zero() {
+                    continue;
+                }
+
+                let gradient = state_gradients[neuron_index]
+                    * rate
+                    * layer
+                        .neuron_activation_function(neuron_index)
+                        .derivative(sum);
+                bias_gradients[neuron_index] = bias_gradients[neuron_index] + gradient;
+
+                let start = neuron_index * row_length;
+                let weights = layer.input_weights(neuron_index);
+                for (offset, source) in sources(layer, neuron_index).enumerate() {
+                    if source == neuron_index {
+                        continue;
+                    }
+
+                    weight_gradients[start + offset] =
+                        weight_gradients[start + offset] + gradient * states[source];
+                    previous_gradients[source] =
+                        previous_gradients[source] + gradient * weights[offset];
+                }
+            }
+
+            // Clamped inputs overwrite the input neurons, so nothing flows back through them.
+            if step.input.is_some() && input_encoding.is_clamped() {
+                previous_gradients[..input_size].fill(F::zero());
+            }
+
+            state_gradients = previous_gradients;
+        }
+
+        Ok(Gradients {
+            loss: loss / graded_values.max(1) as f64,
+            weights: weight_gradients,
+            biases: bias_gradients,
+        })
+    }
+
+    fn apply<F: Float>(&mut self, layer: &mut ThinkingLayer<F>, gradients: &Gradients<F>) {
+        let parameter_count = layer.weights.len() + layer.biases.len();
+        let parameters = layer.weights.iter_mut().chain(&mut layer.biases);
+        let gradients = gradients.weights.iter().chain(&gradients.biases);
+
+        match self.optimizer {
+            Optimizer::Sgd { learning_rate } => {
+                for (parameter, gradient) in parameters.zip(gradients) {
+                    *parameter = *parameter - F::from_f64(learning_rate) * *gradient;
+                }
+            }
+            Optimizer::Adam {
+                learning_rate,
+                beta1,
+                beta2,
+                epsilon,
+            } => {
+                if self.first_moments.len() != parameter_count {
+                    self.first_moments = vec![0.0; parameter_count];
+                    self.second_moments = vec![0.0; parameter_count];
+                    self.steps = 0;
+                }
+
+                self.steps += 1;
+                let first_correction = 1.0 - beta1.powi(self.steps);
+                let second_correction = 1.0 - beta2.powi(self.steps);
+
+                for (parameter, gradient, first_moment, second_moment) in izip!(
+                    parameters,
+                    gradients,
+                    &mut self.first_moments,
+                    &mut self.second_moments
+                ) {
+                    let gradient = gradient.into_f64();
+                    *first_moment = beta1 * *first_moment + (1.0 - beta1) * gradient;
+                    *second_moment = beta2 * *second_moment + (1.0 - beta2) * gradient * gradient;
+
+                    let update = learning_rate * (*first_moment / first_correction)
+                        / ((*second_moment / second_correction).sqrt() + epsilon);
+                    *parameter = *parameter - F::from_f64(update);
+                }
+            }
+        }
+    }
+}
+
+fn weighted_sum<F: Float>(layer: &ThinkingLayer<F>, neuron_index: usize, states: &[F]) -> F {
+    match layer.input_sources(neuron_index) {
+        None => kernel::dot(layer.input_weights(neuron_index), states),
+        Some(sources) => kernel::sparse_dot(layer.input_weights(neuron_index), sources, states),
+    }
+}
+
+/// Source neuron of every incoming weight of a neuron.
+fn sources<F: Float>(
+    layer: &ThinkingLayer<F>,
+    neuron_index: usize,
+) -> impl Iterator<Item = usize> + '_ {
+    match layer.input_sources(neuron_index) {
+        None => Either::Left(0..layer.internal_size()),
+        Some(sources) => Either::Right(sources.iter().map(|source| *source as usize)),
+    }
+}
@@ -0,0 +1,156 @@
+use crate::float::Float;
+use crate::genome::GeneKind;
+use anyhow::bail;
+use rand::seq::index;
+use rand::Rng;
+
+/// How [`Genome::crossover`](crate::genome::Genome::crossover) recombines two parents.
+///
+/// Discrete activation genes and step sizes are always inherited whole from either parent,
+/// interpolating them would pick unrelated palette entries or could turn step sizes negative.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Crossover {
+    /// Every gene of a child is a random weighted average of the parent genes, the sibling gets
+    /// the complementary weights.
+    #[default]
+    Blend,
+    /// Every gene comes from either parent with equal chance.
+    Uniform,
+    /// The genome is cut at `points` random positions and the parents alternate between cuts.
+    NPoint { points: usize },
+    /// Whole neurons, with their bias, delay and incoming connections, come from either parent,
+    /// which keeps functional units intact, see
+    /// [`ThinkingLayer::gene_blocks`](crate::thinking_layer::ThinkingLayer::gene_blocks).
+    NeuronWise,
+    /// Simulated binary crossover, children spread around the parents like one-point crossover
+    /// of binary strings would. Larger distribution indices keep children closer to the parents.
+    ///
+    /// Build it with [`Crossover::simulated_binary`], which checks the distribution index.
+    SimulatedBinary(DistributionIndex),
+    /// Children are copies of their parents.
+    None,
+}
+
+/// Distribution index of [`Crossover::SimulatedBinary`], finite and not negative.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DistributionIndex(f64);
+
+impl DistributionIndex {
+    pub fn get(self) -> f64 {
+        self.0
+    }
+}
+
+impl Crossover {
+    /// [`Crossover::SimulatedBinary`], fails unless `distribution_index` is finite and not
+    /// negative.
+    pub fn simulated_binary(distribution_index: f64) -> anyhow::Result<Self> {
+        if !(distribution_index.is_finite() && distribution_index >= 0.0) {
+            bail!(
+                "Distribution index must be finite and not negative, got {}",
+                distribution_index
+            )
+        }
+
+        Ok(Crossover::SimulatedBinary(DistributionIndex(
+            distribution_index,
+        )))
+    }
+
+    /// Recombines two genomes of the same length into two children.
+    ///
+    /// `kinds` classifies every gene and `blocks` assigns it to a unit of genes that
+    /// [`Crossover::NeuronWise`] keeps together, both in genome order.
+    pub fn recombine<F: Float, R: Rng + ?Sized>(
+        &self,
+        genome_a: &[F],
+        genome_b: &[F],
+        kinds: &[GeneKind],
+        blocks: &[usize],
+        rng: &mut R,
+    ) -> (Vec<F>, Vec<F>) {
+        let length = genome_a.len();
+        // Whether each child takes the gene from the other parent, for the operators that only
+        // ever swap genes.
+        let swaps: Option<Vec<bool>> = match *self {
+            Crossover::Uniform => Some((0..length).map(|_| rng.random()).collect()),
+            Crossover::NPoint { points } => {
+                // Cuts fall between genes, never before the first one.
+                let positions = length.saturating_sub(1);
+                let mut cuts: Vec<usize> = index::sample(rng, positions, points.min(positions))
+                    .into_iter()
+                    .map(|cut| cut + 1)
+                    .collect();
+                cuts.sort_unstable();
+                let mut cuts = cuts.into_iter().peekable();
+                let mut swapped = false;
+                Some(
+                    (0..length)
+                        .map(|index| {
+                            while cuts.next_if(|cut| *cut <= index).is_some() {
+                                swapped = !swapped;
+                            }
+                            swapped
+                        })
+                        .collect(),
+                )
+            }
+            Crossover::NeuronWise => {
+                let block_count = blocks.iter().max().map_or(0, |max| max + 1);
+                let swapped_blocks: Vec<bool> = (0..block_count).map(|_| rng.random()).collect();
+                Some(blocks.iter().map(|block| swapped_blocks[*block]).collect())
+            }
+            Crossover::None => Some(vec![false; length]),
+            Crossover::Blend | Crossover::SimulatedBinary(_) => None,
+        };
+
+        if let Some(swaps) = swaps {
+            return genome_a
+                .iter()
+                .zip(genome_b)
+                .zip(swaps)
+                .map(|((a, b), swap)| if swap { (*b, *a) } else { (*a, *b) })
+                .unzip();
+        }
+
+        genome_a
+            .iter()
+            .zip(genome_b)
+            .zip(kinds)
+            .map(|((&a, &b), kind)| {
+                if matches!(kind, GeneKind::Activation | GeneKind::StepSize) {
+                    return if rng.random() { (b, a) } else { (a, b) };
+                }
+
+                match *self {
+                    Crossover::SimulatedBinary(distribution_index) => {
+                        let spread =
+                            F::from_f64(sbx_spread(rng.random(), distribution_index.get()));
+                        let half = F::from_f64(0.5);
+                        (
+                            half * ((F::one() + spread) * a + (F::one() - spread) * b),
+                            half * ((F::one() - spread) * a + (F::one() + spread) * b),
+                        )
+                    }
+                    _ => {
+                        let variation = F::from_f64(rng.random());
+                        (
+                            a * variation + b * (F::one() - variation),
+                            a * (F::one() - variation) + b * variation,
+                        )
+                    }
+                }
+            })
+            .unzip()
+    }
+}
+
+/// Spread factor of simulated binary crossover for a uniform sample `u`.
+fn sbx_spread(u: f64, distribution_index: f64) -> f64 {
+    let exponent = 1.0 / (distribution_index + 1.0);
+    if u <= 0.5 {
+        (2.0 * u).powf(exponent)
+    } else {
+        (1.0 / (2.0 * (1.0 - u))).powf(exponent)
+    }
+}
@@ -0,0 +1,49 @@
+use crate::kernel;
+use std::fmt::Debug;
+use std::iter::Sum;
+
+/// Floating point type a [`ThinkingLayer`](crate::thinking_layer::ThinkingLayer) can compute in.
+pub trait Float: num_traits::Float + Sum + Default + Debug + Send + Sync + 'static {
+    fn from_f64(value: f64) -> Self;
+    fn into_f64(self) -> f64;
+
+    /// Same value as another float type, rounded if it has less precision.
+    fn cast<T: Float>(self) -> T {
+        T::from_f64(self.into_f64())
+    }
+
+    /// Dot product of two equally long slices, see [`kernel::dot`].
+    fn dot(a: &[Self], b: &[Self]) -> Self {
+        kernel::dot_scalar(a, b)
+    }
+}
+
+impl Float for f32 {
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
+
+    fn into_f64(self) -> f64 {
+        self as f64
+    }
+
+    #[cfg(feature = "simd")]
+    fn dot(a: &[Self], b: &[Self]) -> Self {
+        kernel::simd::dot_f32(a, b)
+    }
+}
+
+impl Float for f64 {
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+
+    fn into_f64(self) -> f64 {
+        self
+    }
+
+    #[cfg(feature = "simd")]
+    fn dot(a: &[Self], b: &[Self]) -> Self {
+        kernel::simd::dot_f64(a, b)
+    }
+}
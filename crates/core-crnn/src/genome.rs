@@ -1,73 +1,255 @@
-use crate::thinking_layer::ThinkingLayer;
-use itertools::izip;
-use rand::{random_iter, rng, Rng};
-use rand_distr::Distribution;
+use crate::crossover::Crossover;
+use crate::float::Float;
+use crate::mutation::{adapt_step_sizes, Mutation};
+use crate::thinking_layer::{clamp_delay_genes, clamp_delays, ThinkingLayer};
+use rand::Rng;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
 
 pub trait Genome {
     type Genome;
     type Child: Genome;
     fn genome(&self) -> Self::Genome;
-    fn load_genome(&mut self, genome: Self::Genome);
-    fn mutate(&mut self, mutation_probability: f64, mutation_strength: f64);
-    fn crossover(genome_a: &Self, genome_b: &Self, n_pairs: usize) -> Vec<Self::Child>;
+    fn load_genome(&mut self, genome: Self::Genome) -> Result<(), GenomeError>;
+    /// Randomly changes genes according to `mutation`, drawing from `rng` so seeded runs can be
+    /// reproduced.
+    fn mutate<R: Rng + ?Sized>(&mut self, mutation: &Mutation, rng: &mut R);
+    /// Builds `n_pairs` pairs of children from two parents with `crossover`, drawing from `rng`.
+    /// Fails if the parents cannot be lined up, e.g. because they take different inputs.
+    fn crossover<R: Rng + ?Sized>(
+        genome_a: &Self,
+        genome_b: &Self,
+        crossover: &Crossover,
+        n_pairs: usize,
+        rng: &mut R,
+    ) -> Result<Vec<Self::Child>, GenomeError>;
 }
 
-impl Genome for ThinkingLayer {
-    type Genome = Vec<f64>;
-    type Child = ThinkingLayer;
+/// What a single gene of a [`ThinkingLayer`] controls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GeneKind {
+    Bias,
+    Delay,
+    /// Discrete index into the activation palette of the layer settings.
+    Activation,
+    Weight,
+    /// Hebbian coefficient of a connection, see [`Plasticity`](crate::layer_settings::Plasticity).
+    Plasticity,
+    /// Weight or bias of a linear output read-out.
+    Readout,
+    /// Mutation step size, see [`SelfAdaptation`](crate::layer_settings::SelfAdaptation).
+    StepSize,
+}
+
+/// What a neuron of a [`ThinkingLayer`] does, which stays the same across layer sizes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NeuronRole {
+    /// Receives input value `i`, the first `input_size` neurons.
+    Input(usize),
+    /// Hidden neuron `i`, counted from the first neuron after the inputs.
+    Hidden(usize),
+    /// Drives output value `i`, the last `output_size` neurons.
+    Output(usize),
+}
+
+/// Identity of a gene that lines it up with the same gene of a layer of another size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GeneId {
+    /// Bias, delay or activation of a neuron.
+    Neuron {
+        neuron: NeuronRole,
+        kind: GeneKind,
+    },
+    /// Weight of a connection, or one of its Hebbian coefficients.
+    Connection {
+        from: NeuronRole,
+        to: NeuronRole,
+        coefficient: Option<usize>,
+    },
+    /// Weight from neuron `from` of linear read-out `output`, or its bias for `None`.
+    Readout {
+        output: usize,
+        from: Option<NeuronRole>,
+    },
+    StepSize(usize),
+}
+
+/// Reasons a genome can be rejected when it is loaded into a model.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GenomeError {
+    LengthMismatch {
+        expected: usize,
+        actual: usize,
+    },
+    NonFiniteGene {
+        index: usize,
+        value: f64,
+    },
+    DelayOutOfRange {
+        neuron: usize,
+        delay: f64,
+    },
+    StepSizeOutOfRange {
+        index: usize,
+        step_size: f64,
+    },
+    /// Crossover parents take or produce different numbers of values.
+    InterfaceMismatch {
+        input_sizes: [usize; 2],
+        output_sizes: [usize; 2],
+    },
+    /// Crossover parents differ in settings or layers, so their genes cannot be lined up.
+    StructureMismatch,
+}
+
+impl Display for GenomeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GenomeError::LengthMismatch { expected, actual } => {
+                write!(
+                    f,
+                    "Genome has {} genes but {} were expected",
+                    actual, expected
+                )
+            }
+            GenomeError::NonFiniteGene { index, value } => {
+                write!(f, "Gene {} is not finite ({})", index, value)
+            }
+            GenomeError::DelayOutOfRange { neuron, delay } => {
+                write!(f, "Delay of neuron {} is out of range ({})", neuron, delay)
+            }
+            GenomeError::StepSizeOutOfRange { index, step_size } => {
+                write!(f, "Step size {} is not positive ({})", index, step_size)
+            }
+            GenomeError::InterfaceMismatch {
+                input_sizes,
+                output_sizes,
+            } => {
+                write!(
+                    f,
+                    "Parents take {:?} inputs and produce {:?} outputs",
+                    input_sizes, output_sizes
+                )
+            }
+            GenomeError::StructureMismatch => {
+                write!(f, "Parents differ in settings or layers")
+            }
+        }
+    }
+}
+
+impl std::error::Error for GenomeError {}
+
+impl<F: Float> Genome for ThinkingLayer<F> {
+    type Genome = Vec<F>;
+    type Child = ThinkingLayer<F>;
 
     fn genome(&self) -> Self::Genome {
-        self.genome().to_vec()
+        self.genome()
     }
 
-    fn load_genome(&mut self, genome: Self::Genome) {
-        self.set_genome(genome);
+    fn load_genome(&mut self, genome: Self::Genome) -> Result<(), GenomeError> {
+        self.set_genome(genome)
     }
 
-    fn mutate(&mut self, mutation_probability: f64, mutation_strength: f64) {
-        let mut rng = rng();
-        self.genome_mut().into_iter().for_each(|gene| {
-            if rng.random::<f64>() < mutation_probability {
-                *gene = *gene + rand_distr::Normal::new(0.0, mutation_strength).unwrap().sample(&mut rng);
-            }
-        });
+    fn mutate<R: Rng + ?Sized>(&mut self, mutation: &Mutation, rng: &mut R) {
+        let palette_size = self.settings().activation_palette.len();
+        let self_adaptation = self.settings().self_adaptation;
+
+        // Step sizes change first so the new ones already shape this mutation.
+        let gene_count = self.genome_length();
+        adapt_step_sizes(&mut self.step_sizes, gene_count, rng);
+        let step_sizes: Vec<f64> = self.step_sizes.iter().map(|size| size.into_f64()).collect();
+        let step_size = |kind| {
+            self_adaptation
+                .step_size_index(kind)
+                .map_or(1.0, |index| step_sizes[index])
+        };
+
+        mutation.apply(self.classified_genes_mut(), palette_size, step_size, rng);
+        clamp_delays(&mut self.delays);
+        self.reset_plastic_weights();
     }
 
-    fn crossover(genome_a: &Self, genome_b: &Self, n_pairs: usize) -> Vec<Self::Child> {
-        let genome_len = genome_a.genome().len();
-        let input_size = genome_a.input_size();
-        let internal_size = genome_a.internal_size();
-        let output_size = genome_a.output_size();
-        let activation_function = genome_a.activation_function().clone();
-        let variations: Vec<f64> = random_iter().take(genome_len).collect();
+    /// Parents of different sizes or connections line up their genes by [`GeneId`]. Every child
+    /// then keeps the size and connections of one parent and recombines the genes both parents
+    /// share.
+    fn crossover<R: Rng + ?Sized>(
+        genome_a: &Self,
+        genome_b: &Self,
+        crossover: &Crossover,
+        n_pairs: usize,
+        rng: &mut R,
+    ) -> Result<Vec<Self::Child>, GenomeError> {
+        if genome_a.input_size() != genome_b.input_size()
+            || genome_a.output_size() != genome_b.output_size()
+        {
+            return Err(GenomeError::InterfaceMismatch {
+                input_sizes: [genome_a.input_size(), genome_b.input_size()],
+                output_sizes: [genome_a.output_size(), genome_b.output_size()],
+            });
+        }
+        if genome_a.settings() != genome_b.settings() {
+            return Err(GenomeError::StructureMismatch);
+        }
+
+        let kinds =
+            |layer: &Self| -> Vec<_> { layer.classified_genes().map(|(kind, _)| kind).collect() };
+        let (parent_a, parent_b) = (genome_a.genome(), genome_b.genome());
+        let (kinds_a, blocks_a) = (kinds(genome_a), genome_a.gene_blocks());
 
-        let children: Vec<_> = (0..n_pairs)
+        if genome_a.internal_size() == genome_b.internal_size()
+            && genome_a.sources() == genome_b.sources()
+        {
+            // Identically wired parents line up gene by gene.
+            return (0..n_pairs)
+                .flat_map(|_| {
+                    let (a, b) =
+                        crossover.recombine(&parent_a, &parent_b, &kinds_a, &blocks_a, rng);
+                    [child_of(genome_a, a), child_of(genome_b, b)]
+                })
+                .collect();
+        }
+
+        let (kinds_b, blocks_b) = (kinds(genome_b), genome_b.gene_blocks());
+        let b_in_a = aligned_genome(genome_b, genome_a);
+        let a_in_b = aligned_genome(genome_a, genome_b);
+        (0..n_pairs)
             .flat_map(|_| {
-                let (a, b): (Vec<_>, Vec<_>) =
-                    izip!(genome_a.genome(), genome_b.genome(), &variations)
-                        .map(|(genome_a, genome_b, variation)| {
-                            (
-                                genome_a * variation + genome_b * (1.0 - variation),
-                                genome_a * (1.0 - variation) + genome_b * variation,
-                            )
-                        })
-                        .unzip();
-
-                vec![a, b]
-            })
-            .map(|genome| {
-                let mut child = ThinkingLayer::new(
-                    input_size,
-                    internal_size,
-                    output_size,
-                    activation_function.clone(),
-                )
-                .unwrap();
-                child.load_genome(genome);
-                child
+                let (a, _) = crossover.recombine(&parent_a, &b_in_a, &kinds_a, &blocks_a, rng);
+                let (_, b) = crossover.recombine(&a_in_b, &parent_b, &kinds_b, &blocks_b, rng);
+                [child_of(genome_a, a), child_of(genome_b, b)]
             })
-            .collect();
-
-        children
+            .collect()
     }
 }
+
+/// Layer with the structure of `parent` driven by `genome`, fails if recombination produced
+/// genes the layer rejects. Delays recombined out of range are clamped.
+fn child_of<F: Float>(
+    parent: &ThinkingLayer<F>,
+    mut genome: Vec<F>,
+) -> Result<ThinkingLayer<F>, GenomeError> {
+    clamp_delay_genes(&mut genome, parent.internal_size(), parent.settings());
+    let mut child = ThinkingLayer::empty(
+        parent.input_size(),
+        parent.internal_size(),
+        parent.output_size(),
+        parent.activation_function().clone(),
+        parent.settings().clone(),
+        parent.sources().to_vec(),
+    );
+    child.load_genome(genome)?;
+    Ok(child)
+}
+
+/// Genome of `target` with every gene `source` also has taken from `source`.
+fn aligned_genome<F: Float>(source: &ThinkingLayer<F>, target: &ThinkingLayer<F>) -> Vec<F> {
+    let source_genes: HashMap<_, _> = source.gene_ids().into_iter().zip(source.genome()).collect();
+    target
+        .gene_ids()
+        .into_iter()
+        .zip(target.genome())
+        .map(|(id, gene)| source_genes.get(&id).copied().unwrap_or(gene))
+        .collect()
+}
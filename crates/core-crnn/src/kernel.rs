@@ -0,0 +1,121 @@
+use crate::float::Float;
+
+/// Number of independent partial sums the dot product accumulates into.
+///
+/// The SIMD kernels use the same lane count and summation order, so they produce bit-for-bit
+/// the same results as [`dot_scalar`].
+pub const LANES: usize = 8;
+
+/// Dot product of two equally long slices, vectorized when the `simd` feature is enabled.
+pub fn dot<F: Float>(a: &[F], b: &[F]) -> F {
+    F::dot(a, b)
+}
+
+/// Dot product using independent partial sums so the compiler can auto-vectorize the loop.
+pub fn dot_scalar<F: Float>(a: &[F], b: &[F]) -> F {
+    let mut sums = [F::zero(); LANES];
+    let a_chunks = a.chunks_exact(LANES);
+    let b_chunks = b.chunks_exact(LANES);
+    let remainder = dot_remainder(a_chunks.remainder(), b_chunks.remainder());
+
+    for (a, b) in a_chunks.zip(b_chunks) {
+        for lane in 0..LANES {
+            sums[lane] = sums[lane] + a[lane] * b[lane];
+        }
+    }
+
+    sums.into_iter().sum::<F>() + remainder
+}
+
+/// Dot product of `weights` with the states of the neurons listed in `sources`.
+pub fn sparse_dot<F: Float>(weights: &[F], sources: &[u32], states: &[F]) -> F {
+    weights
+        .iter()
+        .zip(sources)
+        .fold(F::zero(), |sum, (weight, source)| {
+            sum + *weight * states[*source as usize]
+        })
+}
+
+fn dot_remainder<F: Float>(a: &[F], b: &[F]) -> F {
+    a.iter().zip(b).fold(F::zero(), |sum, (a, b)| sum + *a * *b)
+}
+
+#[cfg(feature = "simd")]
+pub mod simd {
+    use super::dot_scalar;
+
+    pub fn dot_f32(a: &[f32], b: &[f32]) -> f32 {
+        #[cfg(target_arch = "x86_64")]
+        if std::arch::is_x86_feature_detected!("avx") {
+            // Safety: AVX support was checked right above.
+            return unsafe { avx::dot_f32(a, b) };
+        }
+
+        dot_scalar(a, b)
+    }
+
+    pub fn dot_f64(a: &[f64], b: &[f64]) -> f64 {
+        #[cfg(target_arch = "x86_64")]
+        if std::arch::is_x86_feature_detected!("avx") {
+            // Safety: AVX support was checked right above.
+            return unsafe { avx::dot_f64(a, b) };
+        }
+
+        dot_scalar(a, b)
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    mod avx {
+        use crate::kernel::{dot_remainder, LANES};
+        use std::arch::x86_64::*;
+
+        /// One 256 bit register holds all eight lanes.
+        #[target_feature(enable = "avx")]
+        pub unsafe fn dot_f32(a: &[f32], b: &[f32]) -> f32 {
+            let len = a.len().min(b.len());
+            let chunks = len / LANES;
+            let mut sums = _mm256_setzero_ps();
+
+            for chunk in 0..chunks {
+                let offset = chunk * LANES;
+                let a = _mm256_loadu_ps(a.as_ptr().add(offset));
+                let b = _mm256_loadu_ps(b.as_ptr().add(offset));
+                // Separate multiply and add (no FMA) to round exactly like the scalar path.
+                sums = _mm256_add_ps(sums, _mm256_mul_ps(a, b));
+            }
+
+            let mut lanes = [0.0f32; LANES];
+            _mm256_storeu_ps(lanes.as_mut_ptr(), sums);
+            let tail = chunks * LANES;
+
+            lanes.into_iter().sum::<f32>() + dot_remainder(&a[tail..len], &b[tail..len])
+        }
+
+        /// Two 256 bit registers hold lanes 0..4 and 4..8.
+        #[target_feature(enable = "avx")]
+        pub unsafe fn dot_f64(a: &[f64], b: &[f64]) -> f64 {
+            let len = a.len().min(b.len());
+            let chunks = len / LANES;
+            let mut low = _mm256_setzero_pd();
+            let mut high = _mm256_setzero_pd();
+
+            for chunk in 0..chunks {
+                let offset = chunk * LANES;
+                let a_low = _mm256_loadu_pd(a.as_ptr().add(offset));
+                let b_low = _mm256_loadu_pd(b.as_ptr().add(offset));
+                let a_high = _mm256_loadu_pd(a.as_ptr().add(offset + 4));
+                let b_high = _mm256_loadu_pd(b.as_ptr().add(offset + 4));
+                low = _mm256_add_pd(low, _mm256_mul_pd(a_low, b_low));
+                high = _mm256_add_pd(high, _mm256_mul_pd(a_high, b_high));
+            }
+
+            let mut lanes = [0.0f64; LANES];
+            _mm256_storeu_pd(lanes.as_mut_ptr(), low);
+            _mm256_storeu_pd(lanes.as_mut_ptr().add(4), high);
+            let tail = chunks * LANES;
+
+            lanes.into_iter().sum::<f64>() + dot_remainder(&a[tail..len], &b[tail..len])
+        }
+    }
+}
@@ -0,0 +1,287 @@
+use crate::activation_function::ActivationFunction;
+use crate::genome::GeneKind;
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+
+/// Structural options of a [`ThinkingLayer`](crate::thinking_layer::ThinkingLayer) that are
+/// fixed at construction and shared by all of its offspring.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct LayerSettings {
+    #[serde(default)]
+    pub connectivity: Connectivity,
+    /// Activation functions every neuron picks from through an activation gene. When empty, all
+    /// neurons use the activation function of the layer and the genome carries no such gene.
+    #[serde(default)]
+    pub activation_palette: Vec<ActivationFunction>,
+    #[serde(default)]
+    pub update_mode: UpdateMode,
+    #[serde(default)]
+    pub plasticity: Plasticity,
+    #[serde(default)]
+    pub input_encoding: InputEncoding,
+    #[serde(default)]
+    pub output_readout: OutputReadout,
+    #[serde(default)]
+    pub self_adaptation: SelfAdaptation,
+    /// Names of the input channels in order, for feeding them individually. Either empty or one
+    /// per input.
+    #[serde(default)]
+    pub input_names: Vec<String>,
+}
+
+/// Which neurons feed into which.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Connectivity {
+    /// Every neuron receives input from every other neuron.
+    #[default]
+    Dense,
+    /// Every neuron receives input from `fan_in` randomly chosen other neurons.
+    Random { fan_in: usize },
+    /// Every neuron receives input from its `fan_in` nearest neighbours by index, wrapping around.
+    Local { fan_in: usize },
+}
+
+/// How neurons move from their current state to a new activation, and what their delay means.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum UpdateMode {
+    /// A neuron jumps to its new activation on every tick that is a multiple of its delay,
+    /// rounded, and holds its state in between.
+    #[default]
+    Gated,
+    /// A neuron moves `1 / delay` of the way towards its new activation on every tick, making its
+    /// delay a continuous time constant. Delays of at most one behave like [`UpdateMode::Gated`].
+    LeakyIntegrator,
+    /// Continuous-time recurrent network, `delay * ds/dt = activation - s`, integrated in steps
+    /// of `dt` per tick. Time constants below `dt` or
+    /// [`MIN_TIME_CONSTANT`](crate::thinking_layer::MIN_TIME_CONSTANT) behave like the larger of
+    /// the two.
+    ///
+    /// [`Integrator::Euler`] with a `dt` of one is the same as [`UpdateMode::LeakyIntegrator`].
+    Continuous { integrator: Integrator, dt: f64 },
+}
+
+/// How input values reach the first `input_size` neurons.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum InputEncoding {
+    /// Input neurons take on the input values and are never updated by the network.
+    #[default]
+    Clamped,
+    /// Like [`InputEncoding::Clamped`], with every value mapped to `value * scale + offset`.
+    Scaled { scale: f64, offset: f64 },
+    /// Input neurons are updated like all others, the input values are added to their weighted
+    /// sums as a current until the next input arrives.
+    Additive,
+}
+
+impl InputEncoding {
+    /// Whether input neurons are excluded from updates.
+    pub fn is_clamped(&self) -> bool {
+        !matches!(self, InputEncoding::Additive)
+    }
+}
+
+/// How the output of a layer is read from its neurons.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum OutputReadout {
+    /// States of the last `output_size` neurons.
+    #[default]
+    Raw,
+    /// Evolvable linear map from all neuron states to `output_size` values, one bias and
+    /// `internal_size` weights per output. The output neurons are regular neurons then.
+    Linear,
+    /// Softmax of the states of the last `output_size` neurons, for picking discrete actions.
+    Softmax,
+}
+
+impl OutputReadout {
+    pub fn is_raw(&self) -> bool {
+        matches!(self, OutputReadout::Raw)
+    }
+}
+
+/// Whether genomes carry their own mutation step sizes, which scale the noise of
+/// [`Mutation`](crate::mutation::Mutation)s and evolve along with the rest of the genome.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SelfAdaptation {
+    /// Mutations use the strengths they were configured with.
+    #[default]
+    Fixed,
+    /// One step size for all genes.
+    Global,
+    /// One step size for every class in [`SelfAdaptation::ADAPTED_KINDS`].
+    PerGeneClass,
+}
+
+impl SelfAdaptation {
+    /// Continuous gene classes with their own step size, in genome order.
+    pub const ADAPTED_KINDS: [GeneKind; 5] = [
+        GeneKind::Bias,
+        GeneKind::Delay,
+        GeneKind::Weight,
+        GeneKind::Plasticity,
+        GeneKind::Readout,
+    ];
+
+    pub fn step_size_count(&self) -> usize {
+        match self {
+            SelfAdaptation::Fixed => 0,
+            SelfAdaptation::Global => 1,
+            SelfAdaptation::PerGeneClass => Self::ADAPTED_KINDS.len(),
+        }
+    }
+
+    /// Index of the step size that scales genes of `kind`.
+    pub fn step_size_index(&self, kind: GeneKind) -> Option<usize> {
+        match self {
+            SelfAdaptation::Fixed => None,
+            SelfAdaptation::Global => Some(0),
+            SelfAdaptation::PerGeneClass => Self::ADAPTED_KINDS
+                .iter()
+                .position(|adapted| *adapted == kind),
+        }
+    }
+}
+
+/// Whether weights change while a layer is ticked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Plasticity {
+    /// Weights only change through the genome.
+    #[default]
+    Static,
+    /// Every connection carries evolvable coefficients `[eta, a, b, c, d]` and its weight `w`
+    /// changes by `eta * (a * post * pre + b * pre + c * post + d)` on every tick, where `pre`
+    /// is the state of the source neuron before the tick and `post` the state of the target
+    /// neuron after it.
+    Hebbian,
+}
+
+impl Plasticity {
+    pub fn is_plastic(&self) -> bool {
+        !matches!(self, Plasticity::Static)
+    }
+}
+
+/// Numerical integration scheme of [`UpdateMode::Continuous`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Integrator {
+    #[default]
+    Euler,
+    /// Classic fourth order Runge-Kutta, four evaluations of the network per step.
+    RungeKutta4,
+}
+
+impl UpdateMode {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if let UpdateMode::Continuous { dt, .. } = self {
+            if !(dt.is_finite() && *dt > 0.0) {
+                bail!(
+                    "Time step of a continuous thinking layer must be positive, got {}",
+                    dt
+                )
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Connectivity {
+    pub fn is_dense(&self) -> bool {
+        matches!(self, Connectivity::Dense)
+    }
+
+    /// Number of incoming weights every neuron of a layer with `internal_size` neurons has.
+    pub fn fan_in(&self, internal_size: usize) -> usize {
+        match self {
+            Connectivity::Dense => internal_size.saturating_sub(1),
+            Connectivity::Random { fan_in } | Connectivity::Local { fan_in } => *fan_in,
+        }
+    }
+
+    pub fn validate(&self, internal_size: usize) -> anyhow::Result<()> {
+        if !self.is_dense() && self.fan_in(internal_size) == 0 {
+            bail!("Sparse thinking layers need a fan-in of at least one")
+        }
+
+        if self.fan_in(internal_size) > internal_size.saturating_sub(1) {
+            bail!(
+                "Cannot connect each of {} neurons to {} other neurons",
+                internal_size,
+                self.fan_in(internal_size)
+            )
+        }
+
+        Ok(())
+    }
+}
+
+impl LayerSettings {
+    pub fn connectivity(mut self, connectivity: Connectivity) -> Self {
+        self.connectivity = connectivity;
+        self
+    }
+
+    pub fn update_mode(mut self, update_mode: UpdateMode) -> Self {
+        self.update_mode = update_mode;
+        self
+    }
+
+    pub fn plasticity(mut self, plasticity: Plasticity) -> Self {
+        self.plasticity = plasticity;
+        self
+    }
+
+    pub fn input_encoding(mut self, input_encoding: InputEncoding) -> Self {
+        self.input_encoding = input_encoding;
+        self
+    }
+
+    pub fn output_readout(mut self, output_readout: OutputReadout) -> Self {
+        self.output_readout = output_readout;
+        self
+    }
+
+    pub fn self_adaptation(mut self, self_adaptation: SelfAdaptation) -> Self {
+        self.self_adaptation = self_adaptation;
+        self
+    }
+
+    pub fn input_names<S: Into<String>>(
+        mut self,
+        input_names: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.input_names = input_names.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn activation_palette(mut self, activation_palette: Vec<ActivationFunction>) -> Self {
+        self.activation_palette = activation_palette;
+        self
+    }
+
+    /// Whether every neuron carries its own activation gene.
+    pub fn has_activation_genes(&self) -> bool {
+        !self.activation_palette.is_empty()
+    }
+
+    pub fn validate(&self, input_size: usize, internal_size: usize) -> anyhow::Result<()> {
+        self.connectivity.validate(internal_size)?;
+        self.update_mode.validate()?;
+
+        if !self.input_names.is_empty() && self.input_names.len() != input_size {
+            bail!(
+                "Got {} input names for {} inputs",
+                self.input_names.len(),
+                input_size
+            )
+        }
+
+        for (index, name) in self.input_names.iter().enumerate() {
+            if self.input_names[..index].contains(name) {
+                bail!("Input name {:?} is used more than once", name)
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,36 @@
+use crate::float::Float;
+use serde::{Deserialize, Serialize};
+
+/// Dynamic state of a [`ThinkingLayer`](crate::thinking_layer::ThinkingLayer), everything that
+/// changes while it ticks but is not part of its genome.
+///
+/// Taken with [`ThinkingLayer::snapshot`](crate::thinking_layer::ThinkingLayer::snapshot) and put
+/// back with [`ThinkingLayer::restore`](crate::thinking_layer::ThinkingLayer::restore).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LayerState<F: Float = f64> {
+    pub(crate) neuron_states: Vec<F>,
+    pub(crate) internal_tick: usize,
+    /// Adapted weights of plastic layers, empty for static ones.
+    pub(crate) plastic_weights: Vec<F>,
+    /// Last input of layers with additive inputs, empty for others.
+    #[serde(default)]
+    pub(crate) input_current: Vec<F>,
+}
+
+impl<F: Float> LayerState<F> {
+    pub fn neuron_states(&self) -> &[F] {
+        &self.neuron_states
+    }
+
+    pub fn internal_tick(&self) -> usize {
+        self.internal_tick
+    }
+
+    pub fn plastic_weights(&self) -> &[F] {
+        &self.plastic_weights
+    }
+
+    pub fn input_current(&self) -> &[F] {
+        &self.input_current
+    }
+}
@@ -1,3 +1,14 @@
 pub mod activation_function;
+pub mod bptt;
+pub mod crossover;
+pub mod float;
 pub mod genome;
+pub mod kernel;
+pub mod layer_settings;
+pub mod layer_state;
+pub mod mutation;
+pub mod network;
+pub mod persisted_model;
+pub mod stacked_network;
 pub mod thinking_layer;
+pub mod thinking_layer_batch;
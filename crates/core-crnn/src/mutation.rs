@@ -0,0 +1,186 @@
+use crate::float::Float;
+use crate::genome::GeneKind;
+use anyhow::bail;
+use rand::Rng;
+use rand_distr::{Cauchy, Distribution, Normal, StandardNormal};
+
+/// Lower bound of self-adapted step sizes, so exploration never stops completely.
+pub const MIN_STEP_SIZE: f64 = 1e-6;
+
+/// How a gene picked for mutation is changed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MutationOperator {
+    /// Adds normally distributed noise with a standard deviation of `strength`.
+    Gaussian { strength: f64 },
+    /// Adds Cauchy distributed noise, which mostly stays small but now and then jumps far.
+    Cauchy { scale: f64 },
+    /// Replaces the gene with a value drawn uniformly from `min..max`.
+    UniformReset { min: f64, max: f64 },
+    /// Sets the gene to zero, for pruning connections.
+    Prune,
+    /// Moves the gene up or down by a whole number of at most `max_step`, for delays.
+    IntegerStep { max_step: u32 },
+}
+
+/// Applies an operator to every gene of a class with some probability.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MutationRule {
+    /// Class of genes the rule applies to, all of them when `None`.
+    pub kind: Option<GeneKind>,
+    pub probability: f64,
+    pub operator: MutationOperator,
+}
+
+/// Rules [`Genome::mutate`](crate::genome::Genome::mutate) applies to every gene, in order.
+///
+/// Activation genes are discrete, whenever a rule picks one it jumps to a random entry of the
+/// palette regardless of the operator. Step size genes are left to [`adapt_step_sizes`]. Delays
+/// pushed out of range are clamped to [`MIN_DELAY`](crate::thinking_layer::MIN_DELAY) or
+/// [`MAX_DELAY`](crate::thinking_layer::MAX_DELAY).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Mutation {
+    rules: Vec<MutationRule>,
+}
+
+impl Mutation {
+    /// Mutation without any rules, which leaves genomes untouched.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gaussian noise of `strength` on every gene with `probability`.
+    pub fn gaussian(probability: f64, strength: f64) -> anyhow::Result<Self> {
+        Self::new().rule(None, probability, MutationOperator::Gaussian { strength })
+    }
+
+    /// Adds a rule for genes of `kind`, or all genes when `None`.
+    ///
+    /// Fails if `probability` is not between 0 and 1 or the operator's parameters are out of
+    /// range, see [`MutationOperator`].
+    pub fn rule(
+        mut self,
+        kind: impl Into<Option<GeneKind>>,
+        probability: f64,
+        operator: MutationOperator,
+    ) -> anyhow::Result<Self> {
+        if !(0.0..=1.0).contains(&probability) {
+            bail!(
+                "Mutation probability must be between 0 and 1, got {}",
+                probability
+            )
+        }
+        operator.validate()?;
+        self.rules.push(MutationRule {
+            kind: kind.into(),
+            probability,
+            operator,
+        });
+        Ok(self)
+    }
+
+    pub fn rules(&self) -> &[MutationRule] {
+        &self.rules
+    }
+
+    /// Mutates classified genes, activation genes pick from `palette_size` entries and noise is
+    /// scaled by the `step_size` of each gene class.
+    pub fn apply<'a, F: Float, R: Rng + ?Sized>(
+        &self,
+        genes: impl Iterator<Item = (GeneKind, &'a mut F)>,
+        palette_size: usize,
+        step_size: impl Fn(GeneKind) -> f64,
+        rng: &mut R,
+    ) {
+        for (kind, gene) in genes.filter(|(kind, _)| *kind != GeneKind::StepSize) {
+            let step_size = step_size(kind);
+            for rule in &self.rules {
+                if rule.kind.is_some_and(|rule_kind| rule_kind != kind)
+                    || rng.random::<f64>() >= rule.probability
+                {
+                    continue;
+                }
+
+                *gene = match kind {
+                    GeneKind::Activation => F::from_f64(rng.random_range(0..palette_size) as f64),
+                    _ => F::from_f64(rule.operator.apply(gene.into_f64(), step_size, rng)),
+                };
+            }
+        }
+    }
+}
+
+impl MutationOperator {
+    fn validate(&self) -> anyhow::Result<()> {
+        match *self {
+            MutationOperator::Gaussian { strength }
+                if !(strength.is_finite() && strength >= 0.0) =>
+            {
+                bail!(
+                    "Gaussian strength must be finite and not negative, got {}",
+                    strength
+                )
+            }
+            MutationOperator::Cauchy { scale } if !(scale.is_finite() && scale > 0.0) => {
+                bail!("Cauchy scale must be positive and finite, got {}", scale)
+            }
+            MutationOperator::UniformReset { min, max }
+                if !(min.is_finite() && max.is_finite() && min < max) =>
+            {
+                bail!(
+                    "Uniform reset needs a finite range with min < max, got {}..{}",
+                    min,
+                    max
+                )
+            }
+            MutationOperator::IntegerStep { max_step: 0 } => {
+                bail!("Integer step needs a max step of at least 1")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// New value of `gene`, noise is scaled by `step_size` on top of the operator's own strength.
+    pub fn apply<R: Rng + ?Sized>(&self, gene: f64, step_size: f64, rng: &mut R) -> f64 {
+        match *self {
+            MutationOperator::Gaussian { strength } => {
+                gene + Normal::new(0.0, strength * step_size).unwrap().sample(rng)
+            }
+            MutationOperator::Cauchy { scale } => {
+                gene + Cauchy::new(0.0, scale * step_size).unwrap().sample(rng)
+            }
+            MutationOperator::UniformReset { min, max } => rng.random_range(min..max),
+            MutationOperator::Prune => 0.0,
+            MutationOperator::IntegerStep { max_step } => {
+                let step = rng.random_range(1..=max_step) as f64;
+                if rng.random() {
+                    gene + step
+                } else {
+                    gene - step
+                }
+            }
+        }
+    }
+}
+
+/// Log-normal self-adaptation as in evolution strategies, every step size is multiplied by a
+/// random factor shared by all of them and one of its own, using the usual learning rates for
+/// a genome of `gene_count` genes.
+pub fn adapt_step_sizes<F: Float, R: Rng + ?Sized>(
+    step_sizes: &mut [F],
+    gene_count: usize,
+    rng: &mut R,
+) {
+    if step_sizes.is_empty() {
+        return;
+    }
+
+    let gene_count = gene_count.max(1) as f64;
+    let shared_rate = 1.0 / (2.0 * gene_count).sqrt();
+    let individual_rate = 1.0 / (2.0 * gene_count.sqrt()).sqrt();
+    let shared = shared_rate * rng.sample::<f64, _>(StandardNormal);
+
+    for step_size in step_sizes {
+        let factor = (shared + individual_rate * rng.sample::<f64, _>(StandardNormal)).exp();
+        *step_size = F::from_f64((step_size.into_f64() * factor).max(MIN_STEP_SIZE));
+    }
+}
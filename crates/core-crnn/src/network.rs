@@ -0,0 +1,217 @@
+use crate::float::Float;
+use crate::thinking_layer::ThinkingLayer;
+use crate::thinking_layer_batch::ThinkingLayerBatch;
+use std::any::Any;
+use std::slice;
+
+/// Anything that turns inputs into outputs over time and can drive a game, from a single
+/// [`ThinkingLayer`] to composites like [`StackedNetwork`](crate::stacked_network::StackedNetwork).
+pub trait Network<F: Float = f64> {
+    fn input_size(&self) -> usize;
+    fn output_size(&self) -> usize;
+    /// Feeds `input`, if any, and advances by `duration` units of model time, see
+    /// [`ThinkingLayer::tick_for`].
+    fn tick_for(&mut self, input: Option<&[F]>, duration: f64);
+    fn output(&self) -> Vec<F>;
+    /// Starts a new episode, see [`ThinkingLayer::reset_state`].
+    fn reset_state(&mut self);
+
+    /// `count` fresh episodes of this network that tick side by side, for example one per game
+    /// of a training sample.
+    fn samples(&self, count: usize) -> Box<dyn NetworkBatch<F> + Send>
+    where
+        Self: Sized + Clone + Send + 'static,
+    {
+        Box::new(Copies::new(self, count))
+    }
+}
+
+impl<F: Float, N: Network<F> + ?Sized> Network<F> for Box<N> {
+    fn input_size(&self) -> usize {
+        (**self).input_size()
+    }
+
+    fn output_size(&self) -> usize {
+        (**self).output_size()
+    }
+
+    fn tick_for(&mut self, input: Option<&[F]>, duration: f64) {
+        (**self).tick_for(input, duration)
+    }
+
+    fn output(&self) -> Vec<F> {
+        (**self).output()
+    }
+
+    fn reset_state(&mut self) {
+        (**self).reset_state()
+    }
+}
+
+/// Network computing in `F` driven with values of another float type, which are converted on
+/// the way in and out.
+#[derive(Debug, Clone)]
+pub struct Converted<N, F> {
+    network: N,
+    /// Buffer the converted input is gathered in.
+    input: Vec<F>,
+}
+
+impl<N, F> Converted<N, F> {
+    pub fn new(network: N) -> Self {
+        Self {
+            network,
+            input: Vec::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> N {
+        self.network
+    }
+}
+
+impl<F: Float, T: Float, N: Network<F>> Network<T> for Converted<N, F> {
+    fn input_size(&self) -> usize {
+        self.network.input_size()
+    }
+
+    fn output_size(&self) -> usize {
+        self.network.output_size()
+    }
+
+    fn tick_for(&mut self, input: Option<&[T]>, duration: f64) {
+        let Some(input) = input else {
+            return self.network.tick_for(None, duration);
+        };
+
+        self.input.clear();
+        self.input
+            .extend(input.iter().map(|value| value.cast::<F>()));
+        self.network.tick_for(Some(&self.input), duration);
+    }
+
+    fn output(&self) -> Vec<T> {
+        self.network
+            .output()
+            .into_iter()
+            .map(|value| value.cast())
+            .collect()
+    }
+
+    fn reset_state(&mut self) {
+        self.network.reset_state()
+    }
+}
+
+/// Boxes `network` as a network of `T`, wrapping it in [`Converted`] only if it computes in
+/// another float type.
+pub fn boxed<T: Float, F: Float>(
+    network: impl Network<F> + Send + 'static,
+) -> Box<dyn Network<T> + Send> {
+    let mut network: Option<Box<dyn Network<F> + Send>> = Some(Box::new(network));
+    if let Some(same) =
+        (&mut network as &mut dyn Any).downcast_mut::<Option<Box<dyn Network<T> + Send>>>()
+    {
+        return same.take().unwrap();
+    }
+
+    Box::new(Converted::new(network.unwrap()))
+}
+
+/// Members of a batch of networks, ticked together, see [`Network::samples`].
+pub trait NetworkBatch<F: Float = f64> {
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Feeds `inputs`, if any, and advances every member by `duration` units of model time.
+    /// `inputs` holds the inputs of one member after another.
+    fn tick_for(&mut self, inputs: Option<&[F]>, duration: f64);
+    fn output(&self, member: usize) -> &[F];
+}
+
+/// Batch of independent copies for networks that cannot share their parameters.
+struct Copies<N, F> {
+    members: Vec<N>,
+    /// Output of every member, refreshed after each tick.
+    outputs: Vec<Vec<F>>,
+}
+
+impl<F: Float, N: Network<F> + Clone> Copies<N, F> {
+    fn new(network: &N, count: usize) -> Self {
+        let mut network = network.clone();
+        network.reset_state();
+        Self {
+            outputs: vec![network.output(); count],
+            members: vec![network; count],
+        }
+    }
+}
+
+impl<F: Float, N: Network<F>> NetworkBatch<F> for Copies<N, F> {
+    fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    fn tick_for(&mut self, inputs: Option<&[F]>, duration: f64) {
+        for (member, network) in self.members.iter_mut().enumerate() {
+            let input_size = network.input_size();
+            let input = inputs.map(|inputs| &inputs[member * input_size..][..input_size]);
+            network.tick_for(input, duration);
+            self.outputs[member] = network.output();
+        }
+    }
+
+    fn output(&self, member: usize) -> &[F] {
+        &self.outputs[member]
+    }
+}
+
+impl<F: Float> Network<F> for ThinkingLayer<F> {
+    fn input_size(&self) -> usize {
+        self.input_size()
+    }
+
+    fn output_size(&self) -> usize {
+        self.output_size()
+    }
+
+    fn tick_for(&mut self, input: Option<&[F]>, duration: f64) {
+        self.tick_for(input, duration)
+    }
+
+    fn output(&self) -> Vec<F> {
+        self.output()
+    }
+
+    fn reset_state(&mut self) {
+        self.reset_state()
+    }
+
+    /// Samples share one copy of the weights, which [`ThinkingLayerBatch`] applies to all of
+    /// them at once. Layers it does not support are copied instead.
+    fn samples(&self, count: usize) -> Box<dyn NetworkBatch<F> + Send> {
+        let mut layer = self.clone();
+        layer.reset_state();
+        match ThinkingLayerBatch::with_samples(slice::from_ref(&layer), count) {
+            Ok(batch) => Box::new(batch),
+            Err(_) => Box::new(Copies::new(&layer, count)),
+        }
+    }
+}
+
+impl<F: Float> NetworkBatch<F> for ThinkingLayerBatch<F> {
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn tick_for(&mut self, inputs: Option<&[F]>, duration: f64) {
+        self.tick_for(inputs, duration)
+    }
+
+    fn output(&self, member: usize) -> &[F] {
+        self.output(member)
+    }
+}
@@ -0,0 +1,481 @@
+use crate::activation_function::ActivationFunction;
+use crate::float::Float;
+use crate::layer_settings::LayerSettings;
+use crate::thinking_layer::{clamp_delay_genes, ThinkingLayer};
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Version of the on-disk model format itself. Older versions can still be loaded.
+///
+/// 2: Added layer settings and sparse connection sources.
+pub const MODEL_FORMAT_VERSION: u32 = 2;
+/// Version of the flat genome layout, see [`ThinkingLayer::genome`]. Optional genes depend on
+/// the layer settings stored alongside it.
+pub const GENOME_LAYOUT_VERSION: u32 = 1;
+/// First bytes of every binary model file.
+pub const BINARY_MAGIC: [u8; 4] = *b"CRNN";
+
+/// Self-describing snapshot of a [`ThinkingLayer`].
+///
+/// Contains everything needed to rebuild the layer without knowing the
+/// constructor arguments it was created with.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PersistedModel {
+    pub format_version: u32,
+    pub genome_layout_version: u32,
+
+    pub input_size: usize,
+    pub internal_size: usize,
+    pub output_size: usize,
+
+    pub activation_function: ActivationFunction,
+    #[serde(default)]
+    pub settings: LayerSettings,
+    /// Source neurons of sparse layers, see [`ThinkingLayer::sources`].
+    #[serde(default)]
+    pub sources: Vec<u32>,
+
+    pub genome: Vec<f64>,
+    pub neuron_states: Vec<f64>,
+    pub internal_tick: usize,
+
+    #[serde(default)]
+    pub metadata: ModelMetadata,
+}
+
+/// Float width used for genome and neuron states in the binary encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryPrecision {
+    /// Halves the file size, genes are rounded to the nearest `f32`.
+    F32,
+    /// Lossless.
+    F64,
+}
+
+impl BinaryPrecision {
+    fn byte_width(&self) -> u8 {
+        match self {
+            BinaryPrecision::F32 => 4,
+            BinaryPrecision::F64 => 8,
+        }
+    }
+
+    fn from_byte_width(width: u8) -> anyhow::Result<Self> {
+        match width {
+            4 => Ok(BinaryPrecision::F32),
+            8 => Ok(BinaryPrecision::F64),
+            _ => bail!("Unsupported float width of {} bytes", width),
+        }
+    }
+}
+
+/// Parts of the model that have no fixed binary layout, stored as JSON inside the binary file.
+#[derive(Serialize, Deserialize)]
+struct BinaryDescriptor {
+    activation_function: ActivationFunction,
+    #[serde(default)]
+    settings: LayerSettings,
+    metadata: ModelMetadata,
+}
+
+/// Model file of the trainer before versioned models, a score and the genome of a layer with
+/// default settings whose sizes were not stored.
+#[derive(Deserialize)]
+struct LegacyGenome {
+    score: f32,
+    genome: Vec<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ModelMetadata {
+    pub score: Option<f32>,
+    pub generation: Option<usize>,
+    /// Seconds since the unix epoch.
+    pub created_at: Option<u64>,
+    #[serde(default)]
+    pub extra: BTreeMap<String, String>,
+}
+
+impl ModelMetadata {
+    pub fn score(mut self, score: f32) -> Self {
+        self.score = Some(score);
+        self
+    }
+
+    pub fn generation(mut self, generation: usize) -> Self {
+        self.generation = Some(generation);
+        self
+    }
+
+    pub fn created_now(mut self) -> Self {
+        self.created_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .ok()
+            .map(|duration| duration.as_secs());
+        self
+    }
+
+    pub fn extra(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.extra.insert(key.into(), value.into());
+        self
+    }
+}
+
+impl PersistedModel {
+    pub fn from_layer<F: Float>(
+        layer: &ThinkingLayer<F>,
+        metadata: ModelMetadata,
+    ) -> anyhow::Result<Self> {
+        if !layer.activation_function().is_persistable()
+            || !layer
+                .settings()
+                .activation_palette
+                .iter()
+                .all(ActivationFunction::is_persistable)
+        {
+            bail!("Cannot persist a thinking layer using a custom activation function")
+        }
+
+        Ok(Self {
+            format_version: MODEL_FORMAT_VERSION,
+            genome_layout_version: GENOME_LAYOUT_VERSION,
+            input_size: layer.input_size(),
+            internal_size: layer.internal_size(),
+            output_size: layer.output_size(),
+            activation_function: layer.activation_function().clone(),
+            settings: layer.settings().clone(),
+            sources: layer.sources().to_vec(),
+            genome: layer.genome().into_iter().map(F::into_f64).collect(),
+            neuron_states: layer
+                .neuron_states()
+                .iter()
+                .map(|state| state.into_f64())
+                .collect(),
+            internal_tick: layer.internal_tick(),
+            metadata,
+        })
+    }
+
+    /// Checks that the model is consistent and can be loaded by this version.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if !(1..=MODEL_FORMAT_VERSION).contains(&self.format_version) {
+            bail!(
+                "Unsupported model format version {} (expected at most {})",
+                self.format_version,
+                MODEL_FORMAT_VERSION
+            )
+        }
+
+        if self.genome_layout_version != GENOME_LAYOUT_VERSION {
+            bail!(
+                "Unsupported genome layout version {} (expected {})",
+                self.genome_layout_version,
+                GENOME_LAYOUT_VERSION
+            )
+        }
+
+        let interface_size = self.input_size.checked_add(self.output_size);
+        if interface_size.is_none_or(|size| size > self.internal_size) {
+            bail!(
+                "Model has {} internal neurons, too few for its {} inputs and {} outputs",
+                self.internal_size,
+                self.input_size,
+                self.output_size
+            )
+        }
+
+        self.settings
+            .validate(self.input_size, self.internal_size)?;
+        ThinkingLayer::<f64>::validate_sources(self.internal_size, &self.settings, &self.sources)?;
+        ThinkingLayer::<f64>::validate_genome(
+            self.internal_size,
+            self.output_size,
+            &self.settings,
+            &self.genome,
+        )?;
+
+        if self.neuron_states.len() != self.internal_size {
+            bail!(
+                "Model stores {} neuron states but has {} internal neurons",
+                self.neuron_states.len(),
+                self.internal_size
+            )
+        }
+
+        Ok(())
+    }
+
+    /// Rebuilds the layer, converting the stored genes into the requested precision.
+    ///
+    /// Plastic layers start out with the weights of their genome.
+    pub fn into_layer<F: Float>(self) -> anyhow::Result<ThinkingLayer<F>> {
+        self.validate()?;
+
+        let mut layer = ThinkingLayer::empty(
+            self.input_size,
+            self.internal_size,
+            self.output_size,
+            self.activation_function,
+            self.settings,
+            self.sources,
+        );
+        layer.set_genome(self.genome.into_iter().map(F::from_f64).collect())?;
+        layer.neuron_states = self.neuron_states.into_iter().map(F::from_f64).collect();
+        layer.internal_tick = self.internal_tick;
+
+        Ok(layer)
+    }
+
+    /// Reads a model file written before versioned models, which only held a score and a genome.
+    /// The sizes and activation function of the layer were never stored and have to be given.
+    pub fn from_legacy_json(
+        bytes: &[u8],
+        input_size: usize,
+        internal_size: usize,
+        output_size: usize,
+        activation_function: ActivationFunction,
+    ) -> anyhow::Result<Self> {
+        let mut legacy: LegacyGenome =
+            serde_json::from_slice(bytes).context("Malformed legacy model file")?;
+        let settings = LayerSettings::default();
+        // Older trainers let delays drift below zero, which ticks the same as a delay of zero.
+        clamp_delay_genes(&mut legacy.genome, internal_size, &settings);
+        let model = Self {
+            format_version: MODEL_FORMAT_VERSION,
+            genome_layout_version: GENOME_LAYOUT_VERSION,
+            input_size,
+            internal_size,
+            output_size,
+            activation_function,
+            settings,
+            sources: Vec::new(),
+            genome: legacy.genome,
+            neuron_states: vec![0.0; internal_size],
+            internal_tick: 0,
+            metadata: ModelMetadata::default().score(legacy.score),
+        };
+        model.validate()?;
+        Ok(model)
+    }
+
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    pub fn from_json(bytes: &[u8]) -> anyhow::Result<Self> {
+        let model: Self = serde_json::from_slice(bytes).context("Malformed model file")?;
+        model.validate()?;
+        Ok(model)
+    }
+
+    /// Encodes the model as little-endian binary.
+    ///
+    /// Layout: magic, format version, genome layout version, float width, sizes, internal tick,
+    /// length-prefixed JSON descriptor, length-prefixed genome, length-prefixed neuron states,
+    /// length-prefixed `u32` sources and a trailing CRC32 over everything before it.
+    ///
+    /// Always written in the current [`MODEL_FORMAT_VERSION`].
+    pub fn to_binary(&self, precision: BinaryPrecision) -> anyhow::Result<Vec<u8>> {
+        let descriptor = serde_json::to_vec(&BinaryDescriptor {
+            activation_function: self.activation_function.clone(),
+            settings: self.settings.clone(),
+            metadata: self.metadata.clone(),
+        })?;
+
+        let float_count = self.genome.len() + self.neuron_states.len();
+        let mut bytes = Vec::with_capacity(
+            72 + descriptor.len()
+                + float_count * precision.byte_width() as usize
+                + self.sources.len() * 4,
+        );
+
+        bytes.extend_from_slice(&BINARY_MAGIC);
+        bytes.extend_from_slice(&MODEL_FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&self.genome_layout_version.to_le_bytes());
+        bytes.push(precision.byte_width());
+        for value in [
+            self.input_size,
+            self.internal_size,
+            self.output_size,
+            self.internal_tick,
+        ] {
+            bytes.extend_from_slice(&(value as u64).to_le_bytes());
+        }
+
+        bytes.extend_from_slice(&(descriptor.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&descriptor);
+
+        for values in [&self.genome, &self.neuron_states] {
+            bytes.extend_from_slice(&(values.len() as u64).to_le_bytes());
+            for value in values {
+                match precision {
+                    BinaryPrecision::F32 => bytes.extend_from_slice(&(*value as f32).to_le_bytes()),
+                    BinaryPrecision::F64 => bytes.extend_from_slice(&value.to_le_bytes()),
+                }
+            }
+        }
+
+        bytes.extend_from_slice(&(self.sources.len() as u64).to_le_bytes());
+        for source in &self.sources {
+            bytes.extend_from_slice(&source.to_le_bytes());
+        }
+
+        let checksum = crc32fast::hash(&bytes);
+        bytes.extend_from_slice(&checksum.to_le_bytes());
+
+        Ok(bytes)
+    }
+
+    pub fn from_binary(bytes: &[u8]) -> anyhow::Result<Self> {
+        if bytes.len() < BINARY_MAGIC.len() + 4 || bytes[..BINARY_MAGIC.len()] != BINARY_MAGIC {
+            bail!("Not a binary model file")
+        }
+
+        let (content, checksum) = bytes.split_at(bytes.len() - 4);
+        let checksum = u32::from_le_bytes(checksum.try_into()?);
+        if crc32fast::hash(content) != checksum {
+            bail!("Model file checksum mismatch, the file is corrupted")
+        }
+
+        let mut reader = BinaryReader {
+            bytes: content,
+            position: BINARY_MAGIC.len(),
+        };
+
+        let format_version = reader.read_u32()?;
+        let genome_layout_version = reader.read_u32()?;
+        let precision = BinaryPrecision::from_byte_width(reader.read_u8()?)?;
+        let input_size = reader.read_usize()?;
+        let internal_size = reader.read_usize()?;
+        let output_size = reader.read_usize()?;
+        let internal_tick = reader.read_usize()?;
+
+        let descriptor_length = reader.read_usize()?;
+        let descriptor: BinaryDescriptor = serde_json::from_slice(reader.take(descriptor_length)?)
+            .context("Malformed model descriptor")?;
+
+        let genome = reader.read_floats(precision)?;
+        let neuron_states = reader.read_floats(precision)?;
+        let sources = if format_version >= 2 {
+            reader.read_u32s()?
+        } else {
+            Vec::new()
+        };
+
+        if reader.position != content.len() {
+            bail!(
+                "Model file has {} trailing bytes",
+                content.len() - reader.position
+            )
+        }
+
+        let model = Self {
+            format_version,
+            genome_layout_version,
+            input_size,
+            internal_size,
+            output_size,
+            activation_function: descriptor.activation_function,
+            settings: descriptor.settings,
+            sources,
+            genome,
+            neuron_states,
+            internal_tick,
+            metadata: descriptor.metadata,
+        };
+        model.validate()?;
+        Ok(model)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_json()?)
+            .with_context(|| format!("Failed to write model to {}", path.display()))
+    }
+
+    pub fn save_binary(
+        &self,
+        path: impl AsRef<Path>,
+        precision: BinaryPrecision,
+    ) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_binary(precision)?)
+            .with_context(|| format!("Failed to write model to {}", path.display()))
+    }
+
+    /// Loads a model file, detecting whether it is JSON or binary encoded.
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let bytes = fs::read(path)
+            .with_context(|| format!("Failed to read model from {}", path.display()))?;
+
+        if bytes.starts_with(&BINARY_MAGIC) {
+            Self::from_binary(&bytes)
+        } else {
+            Self::from_json(&bytes)
+        }
+        .with_context(|| format!("Invalid model file {}", path.display()))
+    }
+}
+
+struct BinaryReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BinaryReader<'a> {
+    fn take(&mut self, length: usize) -> anyhow::Result<&'a [u8]> {
+        let end = self
+            .position
+            .checked_add(length)
+            .filter(|end| *end <= self.bytes.len())
+            .context("Model file is truncated")?;
+        let slice = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn read_u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn read_u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn read_usize(&mut self) -> anyhow::Result<usize> {
+        let value = u64::from_le_bytes(self.take(8)?.try_into()?);
+        usize::try_from(value).context("Model dimension does not fit into usize")
+    }
+
+    fn read_u32s(&mut self) -> anyhow::Result<Vec<u32>> {
+        let count = self.read_usize()?;
+        let bytes = self.take(count.checked_mul(4).context("Model file is truncated")?)?;
+
+        Ok(bytes
+            .chunks_exact(4)
+            .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect())
+    }
+
+    fn read_floats(&mut self, precision: BinaryPrecision) -> anyhow::Result<Vec<f64>> {
+        let count = self.read_usize()?;
+        let width = precision.byte_width() as usize;
+        let bytes = self.take(
+            count
+                .checked_mul(width)
+                .context("Model file is truncated")?,
+        )?;
+
+        Ok(bytes
+            .chunks_exact(width)
+            .map(|chunk| match precision {
+                BinaryPrecision::F32 => f32::from_le_bytes(chunk.try_into().unwrap()) as f64,
+                BinaryPrecision::F64 => f64::from_le_bytes(chunk.try_into().unwrap()),
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,367 @@
+use crate::crossover::Crossover;
+use crate::float::Float;
+use crate::genome::{Genome, GenomeError};
+use crate::mutation::Mutation;
+use crate::network::Network;
+use crate::thinking_layer::ThinkingLayer;
+use anyhow::bail;
+use rand::Rng;
+
+/// Where an input of a layer or an output of a [`StackedNetwork`] takes its value from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// Input value of the network.
+    Input(usize),
+    /// Output value `index` of the layer at position `layer`.
+    Output { layer: usize, index: usize },
+}
+
+/// Several [`ThinkingLayer`]s wired together, the outputs of some feeding the inputs of others.
+///
+/// Layers are ticked in the order they were added and only read the network inputs and the
+/// outputs of layers added before them, each of which may use its own activation function and
+/// settings. Its genome is the genomes of all layers back to back.
+#[derive(Debug, Clone)]
+pub struct StackedNetwork<F: Float = f64> {
+    input_size: usize,
+    layers: Vec<ThinkingLayer<F>>,
+    /// Source of every input of every layer.
+    wiring: Vec<Vec<Source>>,
+    outputs: Vec<Source>,
+
+    /// Last input of the network and last output of every layer.
+    input: Vec<F>,
+    layer_outputs: Vec<Vec<F>>,
+    /// Buffer the inputs of a layer are gathered in before it ticks.
+    layer_input: Vec<F>,
+}
+
+impl<F: Float> StackedNetwork<F> {
+    /// Network without any layers or outputs taking `input_size` values.
+    pub fn new(input_size: usize) -> Self {
+        Self {
+            input_size,
+            layers: Vec::new(),
+            wiring: Vec::new(),
+            outputs: Vec::new(),
+            input: vec![F::zero(); input_size],
+            layer_outputs: Vec::new(),
+            layer_input: Vec::new(),
+        }
+    }
+
+    /// Feeds every layer into the next, the network has the inputs of the first layer and the
+    /// outputs of the last.
+    pub fn chain(layers: Vec<ThinkingLayer<F>>) -> anyhow::Result<Self> {
+        let Some(first) = layers.first() else {
+            bail!("Cannot chain zero thinking layers")
+        };
+
+        let mut network = Self::new(first.input_size());
+        for (index, layer) in layers.into_iter().enumerate() {
+            let inputs = match index {
+                0 => (0..layer.input_size()).map(Source::Input).collect(),
+                _ => (0..layer.input_size())
+                    .map(|input| Source::Output {
+                        layer: index - 1,
+                        index: input,
+                    })
+                    .collect(),
+            };
+            network = network.layer(layer, inputs)?;
+        }
+
+        let last = network.layers.len() - 1;
+        let outputs = (0..network.layers[last].output_size())
+            .map(|index| Source::Output { layer: last, index })
+            .collect();
+        network.outputs(outputs)
+    }
+
+    /// Adds `layer`, taking its input values from `inputs` in order.
+    pub fn layer(mut self, layer: ThinkingLayer<F>, inputs: Vec<Source>) -> anyhow::Result<Self> {
+        if inputs.len() != layer.input_size() {
+            bail!(
+                "Layer {} has {} inputs but {} sources were given",
+                self.layers.len(),
+                layer.input_size(),
+                inputs.len()
+            )
+        }
+
+        for source in &inputs {
+            self.validate_source(*source)?;
+        }
+
+        self.layer_outputs.push(layer.output());
+        self.layers.push(layer);
+        self.wiring.push(inputs);
+        Ok(self)
+    }
+
+    /// Sets where the output values of the network come from.
+    pub fn outputs(mut self, outputs: Vec<Source>) -> anyhow::Result<Self> {
+        for source in &outputs {
+            self.validate_source(*source)?;
+        }
+
+        self.outputs = outputs;
+        Ok(self)
+    }
+
+    fn validate_source(&self, source: Source) -> anyhow::Result<()> {
+        match source {
+            Source::Input(index) if index >= self.input_size => {
+                bail!(
+                    "Network has {} inputs, there is no input {}",
+                    self.input_size,
+                    index
+                )
+            }
+            Source::Output { layer, .. } if layer >= self.layers.len() => {
+                bail!(
+                    "Layers can only read from layers added before them, there is no layer {} yet",
+                    layer
+                )
+            }
+            Source::Output { layer, index } if index >= self.layers[layer].output_size() => {
+                bail!("Layer {} has no output {}", layer, index)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Feeds `input`, if any, and ticks every layer once.
+    ///
+    /// Panics if `input` does not hold exactly `input_size` values.
+    pub fn tick(&mut self, input: Option<&[F]>) {
+        self.advance(input, None)
+    }
+
+    /// Like [`StackedNetwork::tick`], advancing continuous-time layers by `duration`, see
+    /// [`ThinkingLayer::tick_for`].
+    pub fn tick_for(&mut self, input: Option<&[F]>, duration: f64) {
+        self.advance(input, Some(duration))
+    }
+
+    fn advance(&mut self, input: Option<&[F]>, duration: Option<f64>) {
+        if let Some(input) = input {
+            assert_eq!(
+                input.len(),
+                self.input_size,
+                "Network expects {} input values",
+                self.input_size
+            );
+            self.input.copy_from_slice(input);
+        }
+
+        for (index, (layer, inputs)) in self.layers.iter_mut().zip(&self.wiring).enumerate() {
+            self.layer_input.clear();
+            self.layer_input.extend(
+                inputs
+                    .iter()
+                    .map(|source| read(*source, &self.input, &self.layer_outputs)),
+            );
+
+            match duration {
+                Some(duration) => layer.tick_for(Some(&self.layer_input), duration),
+                None => layer.tick(Some(&self.layer_input)),
+            }
+            self.layer_outputs[index] = layer.output();
+        }
+    }
+
+    pub fn output(&self) -> Vec<F> {
+        self.outputs
+            .iter()
+            .map(|source| read(*source, &self.input, &self.layer_outputs))
+            .collect()
+    }
+
+    /// Resets every layer, see [`ThinkingLayer::reset_state`], and forgets the last input.
+    pub fn reset_state(&mut self) {
+        self.input.fill(F::zero());
+        for (layer, output) in self.layers.iter_mut().zip(&mut self.layer_outputs) {
+            layer.reset_state();
+            *output = layer.output();
+        }
+    }
+
+    pub fn input_size(&self) -> usize {
+        self.input_size
+    }
+
+    pub fn output_size(&self) -> usize {
+        self.outputs.len()
+    }
+
+    pub fn layers(&self) -> &[ThinkingLayer<F>] {
+        &self.layers
+    }
+
+    /// Sources of the inputs of the layer at position `layer`.
+    pub fn layer_inputs(&self, layer: usize) -> &[Source] {
+        &self.wiring[layer]
+    }
+
+    pub fn output_sources(&self) -> &[Source] {
+        &self.outputs
+    }
+
+    pub fn genome_length(&self) -> usize {
+        self.layers.iter().map(ThinkingLayer::genome_length).sum()
+    }
+
+    /// Same wiring and outputs, with `layers` in place of the current ones.
+    fn with_layers(&self, layers: Vec<ThinkingLayer<F>>) -> Self {
+        let mut network = Self {
+            input_size: self.input_size,
+            layer_outputs: layers.iter().map(ThinkingLayer::output).collect(),
+            layers,
+            wiring: self.wiring.clone(),
+            outputs: self.outputs.clone(),
+            input: vec![F::zero(); self.input_size],
+            layer_input: Vec::new(),
+        };
+        network.reset_state();
+        network
+    }
+}
+
+fn read<F: Float>(source: Source, input: &[F], layer_outputs: &[Vec<F>]) -> F {
+    match source {
+        Source::Input(index) => input[index],
+        Source::Output { layer, index } => layer_outputs[layer][index],
+    }
+}
+
+impl<F: Float> Network<F> for StackedNetwork<F> {
+    fn input_size(&self) -> usize {
+        self.input_size()
+    }
+
+    fn output_size(&self) -> usize {
+        self.output_size()
+    }
+
+    fn tick_for(&mut self, input: Option<&[F]>, duration: f64) {
+        self.tick_for(input, duration)
+    }
+
+    fn output(&self) -> Vec<F> {
+        self.output()
+    }
+
+    fn reset_state(&mut self) {
+        self.reset_state()
+    }
+}
+
+impl<F: Float> Genome for StackedNetwork<F> {
+    type Genome = Vec<F>;
+    type Child = StackedNetwork<F>;
+
+    fn genome(&self) -> Self::Genome {
+        self.layers.iter().flat_map(ThinkingLayer::genome).collect()
+    }
+
+    fn load_genome(&mut self, genome: Self::Genome) -> Result<(), GenomeError> {
+        if genome.len() != self.genome_length() {
+            return Err(GenomeError::LengthMismatch {
+                expected: self.genome_length(),
+                actual: genome.len(),
+            });
+        }
+
+        // Check every part first so a bad genome leaves all layers untouched. Errors count genes,
+        // neurons and step sizes across all layers.
+        let (mut offset, mut neuron_offset, mut step_size_offset) = (0, 0, 0);
+        for layer in &self.layers {
+            let part = &genome[offset..offset + layer.genome_length()];
+            ThinkingLayer::<F>::validate_genome(
+                layer.internal_size(),
+                layer.output_size(),
+                layer.settings(),
+                part,
+            )
+            .map_err(|error| match error {
+                GenomeError::NonFiniteGene { index, value } => GenomeError::NonFiniteGene {
+                    index: offset + index,
+                    value,
+                },
+                GenomeError::DelayOutOfRange { neuron, delay } => GenomeError::DelayOutOfRange {
+                    neuron: neuron_offset + neuron,
+                    delay,
+                },
+                GenomeError::StepSizeOutOfRange { index, step_size } => {
+                    GenomeError::StepSizeOutOfRange {
+                        index: step_size_offset + index,
+                        step_size,
+                    }
+                }
+                error => error,
+            })?;
+            offset += layer.genome_length();
+            neuron_offset += layer.internal_size();
+            step_size_offset += layer.settings().self_adaptation.step_size_count();
+        }
+
+        let mut genes = genome.into_iter();
+        for layer in &mut self.layers {
+            let part = genes.by_ref().take(layer.genome_length()).collect();
+            layer.set_genome(part)?;
+        }
+
+        Ok(())
+    }
+
+    fn mutate<R: Rng + ?Sized>(&mut self, mutation: &Mutation, rng: &mut R) {
+        for layer in &mut self.layers {
+            layer.mutate(mutation, rng);
+        }
+    }
+
+    /// Crosses the layers at the same positions, both parents need the same layers wired the
+    /// same way with the same inputs and outputs, while layer sizes may differ.
+    fn crossover<R: Rng + ?Sized>(
+        genome_a: &Self,
+        genome_b: &Self,
+        crossover: &Crossover,
+        n_pairs: usize,
+        rng: &mut R,
+    ) -> Result<Vec<Self::Child>, GenomeError> {
+        if genome_a.input_size != genome_b.input_size
+            || genome_a.outputs.len() != genome_b.outputs.len()
+        {
+            return Err(GenomeError::InterfaceMismatch {
+                input_sizes: [genome_a.input_size, genome_b.input_size],
+                output_sizes: [genome_a.outputs.len(), genome_b.outputs.len()],
+            });
+        }
+        if genome_a.layers.len() != genome_b.layers.len()
+            || genome_a.wiring != genome_b.wiring
+            || genome_a.outputs != genome_b.outputs
+        {
+            return Err(GenomeError::StructureMismatch);
+        }
+
+        let mut layer_children = genome_a
+            .layers
+            .iter()
+            .zip(&genome_b.layers)
+            .map(|(a, b)| {
+                ThinkingLayer::crossover(a, b, crossover, n_pairs, rng).map(Vec::into_iter)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok((0..n_pairs * 2)
+            .map(|_| {
+                let layers = layer_children
+                    .iter_mut()
+                    .map(|children| children.next().unwrap())
+                    .collect();
+                genome_a.with_layers(layers)
+            })
+            .collect())
+    }
+}
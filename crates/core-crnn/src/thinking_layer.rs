@@ -1,87 +1,521 @@
 use crate::activation_function::ActivationFunction;
-use anyhow::bail;
-use rand::{random_iter, random_range};
+use crate::float::Float;
+use crate::genome::{GeneId, GeneKind, GenomeError, NeuronRole};
+use crate::kernel;
+use crate::layer_settings::{
+    Connectivity, InputEncoding, Integrator, LayerSettings, OutputReadout, Plasticity, UpdateMode,
+};
+use crate::layer_state::LayerState;
+use anyhow::{anyhow, bail};
+use itertools::{izip, Either};
+use rand::seq::index;
+use rand::{rng, Rng};
+use std::iter::{once, repeat, repeat_n, repeat_with};
+use std::slice;
+
+/// Smallest delay gene a genome may carry. Delays below one behave like a delay of one.
+pub const MIN_DELAY: f64 = 0.0;
+
+/// Largest delay gene a genome may carry, 2^24, which `f32` represents exactly so layers of
+/// either precision share the same range.
+pub const MAX_DELAY: f64 = 16_777_216.0;
+
+/// Shortest time constant of continuous-time neurons, smaller delays behave like it.
+pub const MIN_TIME_CONSTANT: f64 = 1e-3;
+
+/// Layers with at least this many neurons split their tick across threads when the `parallel`
+/// feature is enabled. Smaller layers are faster on a single core.
+pub const PARALLEL_TICK_THRESHOLD: usize = 256;
+
+/// Number of Hebbian coefficients every connection of a plastic layer carries.
+pub const HEBBIAN_COEFFICIENTS: usize = 5;
+
+/// Plastic weights are clamped to this magnitude so they cannot run away over long episodes.
+pub const MAX_PLASTIC_WEIGHT: f64 = 4.0;
 
 #[derive(Debug, Clone)]
-pub struct ThinkingLayer {
-    input_size: usize,
-    internal_size: usize,
-    output_size: usize,
+pub struct ThinkingLayer<F: Float = f64> {
+    pub(crate) input_size: usize,
+    pub(crate) internal_size: usize,
+    pub(crate) output_size: usize,
+
+    pub(crate) activation_function: ActivationFunction,
+    pub(crate) settings: LayerSettings,
 
-    activation_function: ActivationFunction,
+    /// Incoming weights, one row of [`ThinkingLayer::row_length`] entries per neuron.
+    ///
+    /// Dense layers store the full `internal_size x internal_size` matrix indexed by source
+    /// neuron, its diagonal is always zero so a neuron never feeds into itself. Sparse layers
+    /// store `fan_in` weights per neuron whose source neurons are listed in `sources`.
+    pub(crate) weights: Vec<F>,
+    /// Source neuron of every weight for sparse layers, empty for dense ones.
+    pub(crate) sources: Vec<u32>,
+    /// [`HEBBIAN_COEFFICIENTS`] per weight for plastic layers, empty for static ones.
+    pub(crate) hebbian_coefficients: Vec<F>,
+    pub(crate) biases: Vec<F>,
+    pub(crate) delays: Vec<F>,
+    /// Index into the activation palette of the settings, rounded. Unused without a palette.
+    pub(crate) activation_genes: Vec<F>,
+    /// A bias followed by `internal_size` weights per output for linear read-outs, empty
+    /// otherwise.
+    pub(crate) readout_weights: Vec<F>,
+    /// Self-adapted mutation step sizes, see
+    /// [`SelfAdaptation`](crate::layer_settings::SelfAdaptation).
+    pub(crate) step_sizes: Vec<F>,
 
-    genome: Vec<f64>,
-    neuron_states: Vec<f64>,
+    pub(crate) neuron_states: Vec<F>,
+    /// Latest input values of layers with additive inputs, empty otherwise.
+    pub(crate) input_current: Vec<F>,
+    /// Weights of plastic layers as adapted during the current episode, `weights` keeps the
+    /// genome's ones. Empty for static layers.
+    pub(crate) plastic_weights: Vec<F>,
+    /// States before the current tick, which Hebbian updates need. Empty for static layers.
+    pub(crate) previous_states: Vec<F>,
+    /// Scratch buffer the next states are computed into, avoids allocating on every tick. See
+    /// [`scratch_length_for`] for its size.
+    pub(crate) next_states: Vec<F>,
 
-    internal_tick: usize,
+    pub(crate) internal_tick: usize,
 }
 
-impl ThinkingLayer {
+impl<F: Float> ThinkingLayer<F> {
     pub fn new(
         input_count: usize,
         internal_count: usize,
         output_count: usize,
         activation_function: ActivationFunction,
+    ) -> anyhow::Result<Self> {
+        Self::with_settings(
+            input_count,
+            internal_count,
+            output_count,
+            activation_function,
+            LayerSettings::default(),
+        )
+    }
+
+    pub fn with_settings(
+        input_count: usize,
+        internal_count: usize,
+        output_count: usize,
+        activation_function: ActivationFunction,
+        settings: LayerSettings,
+    ) -> anyhow::Result<Self> {
+        Self::with_rng(
+            input_count,
+            internal_count,
+            output_count,
+            activation_function,
+            settings,
+            &mut rng(),
+        )
+    }
+
+    /// Like [`ThinkingLayer::with_settings`], drawing the random genome and connections from
+    /// `rng` so the same seed always builds the same layer.
+    pub fn with_rng<R: Rng + ?Sized>(
+        input_count: usize,
+        internal_count: usize,
+        output_count: usize,
+        activation_function: ActivationFunction,
+        settings: LayerSettings,
+        rng: &mut R,
     ) -> anyhow::Result<Self> {
         if input_count + output_count > internal_count {
             bail!("Cannot create thinking layer with fewer neurons than input and output values")
         }
+        settings.validate(input_count, internal_count)?;
 
-        Ok(Self {
-            input_size: input_count,
-            internal_size: internal_count,
-            output_size: output_count,
-            genome: (0..internal_count)
-                .flat_map(|_| {
-                    let mut data = vec![random_range(-0.1..0.1), random_range(1.0..3.0)];
-                    data.extend(
-                        // Random weights in from -0.1 to 0.1 (n-1xf64)
-                        random_iter::<f64>()
-                            .take(internal_count - 1)
-                            .map(|x| x / 10.0 - 0.05)
-                            .collect::<Vec<_>>(),
-                    );
-                    data
-                })
-                .collect(),
-            neuron_states: vec![0.0; internal_count],
+        let fan_in = settings.connectivity.fan_in(internal_count);
+        let palette_size = settings.activation_palette.len();
+        let plastic = settings.plasticity.is_plastic();
+        let mut genome = Vec::new();
+        for _ in 0..internal_count {
+            genome.extend([rng.random_range(-0.1..0.1), rng.random_range(1.0..3.0)]);
+            if palette_size > 0 {
+                genome.push(rng.random_range(0..palette_size) as f64);
+            }
+            // Random weights in from -0.1 to 0.1 (fan_in x f64)
+            genome.extend((0..fan_in).map(|_| rng.random::<f64>() / 10.0 - 0.05));
+            if plastic {
+                genome.extend(
+                    (0..fan_in * HEBBIAN_COEFFICIENTS).map(|_| rng.random_range(-0.1..0.1)),
+                );
+            }
+        }
+        // Read-out weights in the same range as the internal ones
+        genome.extend(
+            (0..readout_length_for(internal_count, output_count, &settings))
+                .map(|_| rng.random::<f64>() / 10.0 - 0.05),
+        );
+        // Step sizes start out leaving the configured mutation strengths as they are
+        genome.extend(repeat_n(1.0, settings.self_adaptation.step_size_count()));
+        let genome = genome.into_iter().map(F::from_f64).collect();
+
+        let sources = build_sources(settings.connectivity, internal_count, rng);
+        let mut layer = Self::empty(
+            input_count,
+            internal_count,
+            output_count,
+            activation_function,
+            settings,
+            sources,
+        );
+        layer.scatter_genome(genome);
+        Ok(layer)
+    }
+
+    /// Builds a layer with all genes and states set to zero.
+    ///
+    /// `sources` has to match the connectivity of `settings`, see [`ThinkingLayer::validate_sources`].
+    pub(crate) fn empty(
+        input_size: usize,
+        internal_size: usize,
+        output_size: usize,
+        activation_function: ActivationFunction,
+        settings: LayerSettings,
+        sources: Vec<u32>,
+    ) -> Self {
+        let row_length = row_length_for(internal_size, &settings);
+        let scratch_length = scratch_length_for(internal_size, &settings);
+        let plastic = settings.plasticity.is_plastic();
+        let plastic_length = |length| if plastic { length } else { 0 };
+        let readout_length = readout_length_for(internal_size, output_size, &settings);
+        let step_size_count = settings.self_adaptation.step_size_count();
+        let input_current_length = match settings.input_encoding {
+            InputEncoding::Additive => input_size,
+            _ => 0,
+        };
+
+        Self {
+            input_size,
+            internal_size,
+            output_size,
             activation_function,
+            settings,
+            weights: vec![F::zero(); internal_size * row_length],
+            sources,
+            hebbian_coefficients: vec![
+                F::zero();
+                plastic_length(
+                    internal_size * row_length * HEBBIAN_COEFFICIENTS
+                )
+            ],
+            biases: vec![F::zero(); internal_size],
+            delays: vec![F::zero(); internal_size],
+            activation_genes: vec![F::zero(); internal_size],
+            readout_weights: vec![F::zero(); readout_length],
+            step_sizes: vec![F::zero(); step_size_count],
+            neuron_states: vec![F::zero(); internal_size],
+            input_current: vec![F::zero(); input_current_length],
+            plastic_weights: vec![F::zero(); plastic_length(internal_size * row_length)],
+            previous_states: vec![F::zero(); plastic_length(internal_size)],
+            next_states: vec![F::zero(); scratch_length],
             internal_tick: 1,
-        })
+        }
+    }
+
+    /// Copies this layer into one computing with a different float precision.
+    pub fn convert<T: Float>(&self) -> ThinkingLayer<T> {
+        ThinkingLayer {
+            input_size: self.input_size,
+            internal_size: self.internal_size,
+            output_size: self.output_size,
+            activation_function: self.activation_function.clone(),
+            settings: self.settings.clone(),
+            weights: convert_floats(&self.weights),
+            sources: self.sources.clone(),
+            hebbian_coefficients: convert_floats(&self.hebbian_coefficients),
+            biases: convert_floats(&self.biases),
+            delays: convert_floats(&self.delays),
+            activation_genes: convert_floats(&self.activation_genes),
+            readout_weights: convert_floats(&self.readout_weights),
+            step_sizes: convert_floats(&self.step_sizes),
+            neuron_states: convert_floats(&self.neuron_states),
+            input_current: convert_floats(&self.input_current),
+            plastic_weights: convert_floats(&self.plastic_weights),
+            previous_states: convert_floats(&self.previous_states),
+            next_states: convert_floats(&self.next_states),
+            internal_tick: self.internal_tick,
+        }
     }
 
-    pub fn tick(&mut self, input: Option<Vec<f64>>) {
-        if self.internal_tick == 0 {
-            self.internal_tick = 1;
+    /// Feeds `input` to the input neurons, if any, and updates the network once.
+    ///
+    /// Panics if `input` does not hold exactly `input_size` values, see
+    /// [`ThinkingLayer::set_input`] for a checked alternative.
+    pub fn tick(&mut self, input: Option<&[F]>) {
+        if let Some(input) = input {
+            self.feed(input);
         }
 
+        self.step(self.settings.update_mode);
+    }
+
+    /// Advances a continuous-time layer by `duration` units of model time, in as few equally
+    /// long steps as possible that are no longer than its `dt`.
+    ///
+    /// Other layers have no notion of time and tick once. Panics if `duration` is not positive
+    /// and finite.
+    pub fn tick_for(&mut self, input: Option<&[F]>, duration: f64) {
+        let (steps, update_mode) = steps_for(self.settings.update_mode, duration);
         if let Some(input) = input {
-            self.neuron_states.splice(0..self.input_size, input);
+            self.feed(input);
+        }
+
+        for _ in 0..steps {
+            self.step(update_mode);
+        }
+    }
+
+    fn feed(&mut self, input: &[F]) {
+        if let Err(error) = self.set_input(input) {
+            panic!("{}", error)
+        }
+    }
+
+    /// Feeds all input values at once, taking effect on the next tick.
+    pub fn set_input(&mut self, input: &[F]) -> anyhow::Result<()> {
+        if input.len() != self.input_size {
+            bail!(
+                "Thinking layer expects {} input values, got {}",
+                self.input_size,
+                input.len()
+            )
         }
 
-        let exclude_input_range = self.input_size..self.internal_size;
+        write_input(
+            self.settings.input_encoding,
+            input,
+            &mut self.neuron_states[..self.input_size],
+            &mut self.input_current,
+        );
+        Ok(())
+    }
+
+    /// Feeds a single input value, leaving all other inputs as they are.
+    pub fn set_input_channel(&mut self, channel: usize, value: F) -> anyhow::Result<()> {
+        if channel >= self.input_size {
+            bail!(
+                "Thinking layer has {} input channels, there is no channel {}",
+                self.input_size,
+                channel
+            )
+        }
+
+        write_input(
+            self.settings.input_encoding,
+            &[value],
+            &mut self.neuron_states[channel..channel + 1],
+            self.input_current
+                .get_mut(channel..channel + 1)
+                .unwrap_or_default(),
+        );
+        Ok(())
+    }
+
+    /// Feeds the input channels named in `inputs`, see [`LayerSettings::input_names`]. Feeds
+    /// nothing if any name is unknown.
+    pub fn set_named_inputs(&mut self, inputs: &[(&str, F)]) -> anyhow::Result<()> {
+        let channels = inputs
+            .iter()
+            .map(|(name, _)| {
+                self.input_channel(name)
+                    .ok_or_else(|| anyhow!("Thinking layer has no input channel named {:?}", name))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
 
-        let new_states: Vec<_> = self.delays()[exclude_input_range.clone()]
-            .into_iter()
+        for (channel, (_, value)) in channels.into_iter().zip(inputs) {
+            self.set_input_channel(channel, *value)?;
+        }
+
+        Ok(())
+    }
+
+    /// Index of the input channel called `name`.
+    pub fn input_channel(&self, name: &str) -> Option<usize> {
+        self.settings
+            .input_names
+            .iter()
+            .position(|input_name| input_name == name)
+    }
+
+    /// Number of leading neurons the network never updates itself.
+    fn clamped_inputs(&self) -> usize {
+        if self.settings.input_encoding.is_clamped() {
+            self.input_size
+        } else {
+            0
+        }
+    }
+
+    fn step(&mut self, update_mode: UpdateMode) {
+        let plastic = self.settings.plasticity.is_plastic();
+        if plastic {
+            self.previous_states.copy_from_slice(&self.neuron_states);
+        }
+
+        LayerParameters {
+            clamped_inputs: self.clamped_inputs(),
+            input_current: &self.input_current,
+            activation_function: &self.activation_function,
+            weights: if plastic {
+                &self.plastic_weights
+            } else {
+                &self.weights
+            },
+            sources: &self.sources,
+            biases: &self.biases,
+            delays: &self.delays,
+            activation_genes: &self.activation_genes,
+            activation_palette: &self.settings.activation_palette,
+            update_mode,
+        }
+        .step(
+            &mut self.neuron_states,
+            &mut self.next_states,
+            slice::from_mut(&mut self.internal_tick),
+        );
+
+        if plastic {
+            self.hebbian_update();
+        }
+    }
+
+    /// Adapts the plastic weights to the tick that just happened.
+    fn hebbian_update(&mut self) {
+        let row_length = self.row_length().max(1);
+        let dense = self.settings.connectivity.is_dense();
+        let max_weight = F::from_f64(MAX_PLASTIC_WEIGHT);
+        let clamped_inputs = self.clamped_inputs();
+
+        for (neuron_index, (row, coefficients)) in self
+            .plastic_weights
+            .chunks_mut(row_length)
+            .zip(
+                self.hebbian_coefficients
+                    .chunks(row_length * HEBBIAN_COEFFICIENTS),
+            )
             .enumerate()
-            .map(|(neuron_index, delay)| {
-                let neuron_index = neuron_index + self.input_size;
-                if self.internal_tick % delay.round().max(1.0) as usize == 0 {
-                    self.activate_neuron(neuron_index)
+            .skip(clamped_inputs)
+        {
+            let post = self.neuron_states[neuron_index];
+            for (offset, (weight, coefficients)) in row
+                .iter_mut()
+                .zip(coefficients.chunks_exact(HEBBIAN_COEFFICIENTS))
+                .enumerate()
+            {
+                let source = if dense {
+                    offset
                 } else {
-                    self.neuron_states[neuron_index]
+                    self.sources[neuron_index * row_length + offset] as usize
+                };
+                if source == neuron_index {
+                    continue;
                 }
-            })
-            .collect();
 
-        self.neuron_states.splice(exclude_input_range, new_states);
+                let pre = self.previous_states[source];
+                let &[eta, a, b, c, d] = coefficients else {
+                    continue;
+                };
+                *weight = (*weight + eta * (a * post * pre + b * pre + c * post + d))
+                    .max(-max_weight)
+                    .min(max_weight);
+            }
+        }
+    }
+
+    /// Restores the weights of a plastic layer to the ones in its genome, for example between
+    /// episodes. Static layers are not affected.
+    pub fn reset_plastic_weights(&mut self) {
+        if self.settings.plasticity.is_plastic() {
+            self.plastic_weights.copy_from_slice(&self.weights);
+        }
+    }
+
+    /// Clears the dynamic state so the layer behaves like a freshly loaded one: all neurons are
+    /// zero, the tick counter starts over and plastic weights return to the genome's ones.
+    pub fn reset_state(&mut self) {
+        self.neuron_states.fill(F::zero());
+        self.input_current.fill(F::zero());
+        self.internal_tick = 1;
+        self.reset_plastic_weights();
+    }
+
+    /// Copies the dynamic state, see [`LayerState`].
+    pub fn snapshot(&self) -> LayerState<F> {
+        LayerState {
+            neuron_states: self.neuron_states.clone(),
+            internal_tick: self.internal_tick,
+            plastic_weights: self.plastic_weights.clone(),
+            input_current: self.input_current.clone(),
+        }
+    }
+
+    /// Puts back a state taken from a layer with the same topology.
+    pub fn restore(&mut self, state: &LayerState<F>) -> anyhow::Result<()> {
+        if state.neuron_states.len() != self.neuron_states.len()
+            || state.plastic_weights.len() != self.plastic_weights.len()
+            || state.input_current.len() != self.input_current.len()
+        {
+            bail!("Cannot restore a state taken from a thinking layer with a different topology")
+        }
+
+        self.neuron_states.copy_from_slice(&state.neuron_states);
+        self.internal_tick = state.internal_tick;
+        self.plastic_weights.copy_from_slice(&state.plastic_weights);
+        self.input_current.copy_from_slice(&state.input_current);
+        Ok(())
+    }
+
+    /// Weights the layer currently ticks with, which differ from [`ThinkingLayer::weights`] for
+    /// plastic layers adapting during an episode.
+    pub fn effective_weights(&self) -> &[F] {
+        if self.settings.plasticity.is_plastic() {
+            &self.plastic_weights
+        } else {
+            &self.weights
+        }
+    }
+
+    /// Biases and weights of a linear read-out, one row of `internal_size + 1` values per
+    /// output starting with the bias. Empty for other read-outs.
+    pub fn readout_weights(&self) -> &[F] {
+        &self.readout_weights
+    }
+
+    /// Self-adapted mutation step sizes, one per entry of
+    /// [`SelfAdaptation::ADAPTED_KINDS`](crate::layer_settings::SelfAdaptation::ADAPTED_KINDS) or
+    /// a single one, empty without self-adaptation.
+    pub fn step_sizes(&self) -> &[F] {
+        &self.step_sizes
+    }
 
-        self.internal_tick = self.internal_tick.overflowing_add(1).0;
+    /// Hebbian coefficients of a plastic layer, [`HEBBIAN_COEFFICIENTS`] per weight.
+    pub fn hebbian_coefficients(&self) -> &[F] {
+        &self.hebbian_coefficients
     }
 
-    pub fn output(&self) -> Vec<f64> {
+    /// Output values according to the output read-out of the settings.
+    pub fn output(&self) -> Vec<F> {
         let output_range = self.internal_size - self.output_size..self.internal_size;
-        self.neuron_states[output_range].to_vec()
+        let raw = &self.neuron_states[output_range];
+
+        match self.settings.output_readout {
+            OutputReadout::Raw => raw.to_vec(),
+            OutputReadout::Linear => self
+                .readout_weights
+                .chunks_exact(self.internal_size + 1)
+                .map(|row| row[0] + kernel::dot(&row[1..], &self.neuron_states))
+                .collect(),
+            OutputReadout::Softmax => {
+                // Shifting by the maximum keeps the exponentials from overflowing.
+                let max = raw.iter().copied().fold(F::neg_infinity(), F::max);
+                let exponentials: Vec<F> = raw.iter().map(|state| (*state - max).exp()).collect();
+                let sum = exponentials.iter().copied().sum::<F>();
+                exponentials.into_iter().map(|value| value / sum).collect()
+            }
+        }
     }
 
     pub fn input_size(&self) -> usize {
@@ -96,64 +530,859 @@ impl ThinkingLayer {
         self.output_size
     }
 
-    pub fn neuron_states(&self) -> &[f64] {
+    pub fn neuron_states(&self) -> &[F] {
         &self.neuron_states
     }
 
-    pub fn bias(&self, index: usize) -> f64 {
-        self.genome[index * self.neuron_data_length()] // Bias is the first element
+    pub fn internal_tick(&self) -> usize {
+        self.internal_tick
     }
 
-    pub fn delays(&self) -> Vec<&f64> {
-        self.genome
-            .iter()
-            .skip(1) // delay is the second element
-            .step_by(self.neuron_data_length())
-            .collect()
+    pub fn bias(&self, index: usize) -> F {
+        self.biases[index]
+    }
+
+    pub fn biases(&self) -> &[F] {
+        &self.biases
+    }
+
+    pub fn delays(&self) -> &[F] {
+        &self.delays
+    }
+
+    /// Raw activation genes, see [`ThinkingLayer::neuron_activation_function`].
+    pub fn activation_genes(&self) -> &[F] {
+        &self.activation_genes
+    }
+
+    /// Activation function a neuron applies, picked from the palette by its activation gene.
+    pub fn neuron_activation_function(&self, neuron_index: usize) -> &ActivationFunction {
+        neuron_activation_function(
+            &self.activation_function,
+            &self.settings.activation_palette,
+            self.activation_genes[neuron_index],
+        )
+    }
+
+    /// Incoming weights of a neuron.
+    ///
+    /// Dense layers index them by source neuron with a zero self-connection, sparse layers
+    /// list the sources in [`ThinkingLayer::input_sources`].
+    pub fn input_weights(&self, neuron_index: usize) -> &[F] {
+        let row_length = self.row_length();
+        let start = row_length * neuron_index;
+        &self.weights[start..start + row_length]
+    }
+
+    /// Source neurons of [`ThinkingLayer::input_weights`], `None` for dense layers.
+    pub fn input_sources(&self, neuron_index: usize) -> Option<&[u32]> {
+        if self.settings.connectivity.is_dense() {
+            return None;
+        }
+
+        let row_length = self.row_length();
+        let start = row_length * neuron_index;
+        Some(&self.sources[start..start + row_length])
+    }
+
+    /// All weights, see [`ThinkingLayer::input_weights`] for the row layout.
+    pub fn weights(&self) -> &[F] {
+        &self.weights
+    }
+
+    /// All sources of a sparse layer, empty for dense ones.
+    pub fn sources(&self) -> &[u32] {
+        &self.sources
+    }
+
+    pub fn settings(&self) -> &LayerSettings {
+        &self.settings
+    }
+
+    /// Number of weights stored per neuron.
+    pub fn row_length(&self) -> usize {
+        row_length_for(self.internal_size, &self.settings)
+    }
+
+    /// Number of genes a layer with `internal_size` neurons and `settings` carries, `None` if
+    /// it does not fit in a `usize`.
+    pub fn genome_length_for(
+        internal_size: usize,
+        output_size: usize,
+        settings: &LayerSettings,
+    ) -> Option<usize> {
+        let neuron_genes = settings
+            .connectivity
+            .fan_in(internal_size)
+            .checked_mul(genes_per_connection(settings))?
+            .checked_add(2 + settings.has_activation_genes() as usize)?;
+        let readout_genes = match settings.output_readout {
+            OutputReadout::Linear => output_size.checked_mul(internal_size.checked_add(1)?)?,
+            OutputReadout::Raw | OutputReadout::Softmax => 0,
+        };
+
+        internal_size
+            .checked_mul(neuron_genes)?
+            .checked_add(readout_genes)?
+            .checked_add(settings.self_adaptation.step_size_count())
+    }
+
+    pub fn genome_length(&self) -> usize {
+        Self::genome_length_for(self.internal_size, self.output_size, &self.settings)
+            .expect("Genome of an existing layer fits in memory")
     }
 
-    pub fn input_weights(&self, neuron_index: usize) -> &[f64] {
-        let start = 2 + self.neuron_data_length() * neuron_index;
-        let end = self.neuron_data_length() * (neuron_index + 1);
-        &self.genome[start..end]
+    /// Functional unit of every gene in genome order: the genes of neuron `i` form block `i`,
+    /// followed by one block per read-out row and one for the step sizes.
+    pub fn gene_blocks(&self) -> Vec<usize> {
+        let neuron_genes = neuron_data_length_for(self.internal_size, &self.settings);
+        let readout_row_length = self.internal_size + 1;
+        let readout_rows = self.readout_weights.len() / readout_row_length;
+
+        (0..self.internal_size)
+            .flat_map(|neuron_index| repeat_n(neuron_index, neuron_genes))
+            .chain(
+                (0..readout_rows)
+                    .flat_map(|row| repeat_n(self.internal_size + row, readout_row_length)),
+            )
+            .chain(repeat_n(
+                self.internal_size + readout_rows,
+                self.step_sizes.len(),
+            ))
+            .collect()
     }
 
-    fn neuron_data_length(&self) -> usize {
-        2 + self.internal_size - 1
+    /// Role of the neuron at `index`, see [`NeuronRole`].
+    pub fn neuron_role(&self, index: usize) -> NeuronRole {
+        let first_output = self.internal_size - self.output_size;
+        if index < self.input_size {
+            NeuronRole::Input(index)
+        } else if index < first_output {
+            NeuronRole::Hidden(index - self.input_size)
+        } else {
+            NeuronRole::Output(index - first_output)
+        }
     }
 
-    fn activate_neuron(&self, neuron_index: usize) -> f64 {
-        let mut weights = self.input_weights(neuron_index).iter();
-        let states = self.neuron_states();
+    /// Identity of every gene in genome order, see [`GeneId`].
+    pub fn gene_ids(&self) -> Vec<GeneId> {
+        let fan_in = self.settings.connectivity.fan_in(self.internal_size);
+        let coefficients = if self.settings.plasticity.is_plastic() {
+            HEBBIAN_COEFFICIENTS
+        } else {
+            0
+        };
+        let mut kinds = vec![GeneKind::Bias, GeneKind::Delay];
+        if self.settings.has_activation_genes() {
+            kinds.push(GeneKind::Activation);
+        }
 
-        let mut sum = 0.0;
+        let mut ids = Vec::with_capacity(self.genome_length());
+        for neuron_index in 0..self.internal_size {
+            let to = self.neuron_role(neuron_index);
+            let sources: Vec<_> = if self.settings.connectivity.is_dense() {
+                (0..self.internal_size)
+                    .filter(|source| *source != neuron_index)
+                    .map(|source| self.neuron_role(source))
+                    .collect()
+            } else {
+                self.sources[neuron_index * fan_in..(neuron_index + 1) * fan_in]
+                    .iter()
+                    .map(|source| self.neuron_role(*source as usize))
+                    .collect()
+            };
 
-        for state in &states[..neuron_index] {
-            sum += weights.next().unwrap() * state;
+            ids.extend(kinds.iter().map(|kind| GeneId::Neuron {
+                neuron: to,
+                kind: *kind,
+            }));
+            ids.extend(sources.iter().map(|from| GeneId::Connection {
+                from: *from,
+                to,
+                coefficient: None,
+            }));
+            ids.extend(sources.iter().flat_map(|from| {
+                (0..coefficients).map(move |coefficient| GeneId::Connection {
+                    from: *from,
+                    to,
+                    coefficient: Some(coefficient),
+                })
+            }));
         }
 
-        for state in &states[(neuron_index + 1)..] {
-            sum += weights.next().unwrap() * state;
+        let readout_rows = self.readout_weights.len() / (self.internal_size + 1);
+        for output in 0..readout_rows {
+            ids.push(GeneId::Readout { output, from: None });
+            ids.extend((0..self.internal_size).map(|from| GeneId::Readout {
+                output,
+                from: Some(self.neuron_role(from)),
+            }));
         }
+        ids.extend((0..self.step_sizes.len()).map(GeneId::StepSize));
+        ids
+    }
+
+    /// Flat genome view, `[bias, delay, activation, weights..., hebbian coefficients...]` per
+    /// neuron followed by the read-out weights and step sizes. The activation gene only exists
+    /// with an activation palette, the Hebbian coefficients only for plastic layers, read-out
+    /// weights only for linear read-outs and step sizes only with self-adaptation. Dense layers
+    /// leave the self-connection out.
+    pub fn genome(&self) -> Vec<F> {
+        self.classified_genes().map(|(_, gene)| *gene).collect()
+    }
+
+    /// Every gene together with what it controls, in the same order as [`ThinkingLayer::genome`].
+    pub fn classified_genes(&self) -> impl Iterator<Item = (GeneKind, &F)> {
+        let dense = self.settings.connectivity.is_dense();
+        let activation_genes = self.settings.has_activation_genes();
+        let row_length = self.row_length().max(1);
+        let coefficient_rows = if self.settings.plasticity.is_plastic() {
+            Either::Left(
+                self.hebbian_coefficients
+                    .chunks(row_length * HEBBIAN_COEFFICIENTS),
+            )
+        } else {
+            Either::Right(repeat(&[][..]))
+        };
+
+        izip!(
+            &self.biases,
+            &self.delays,
+            &self.activation_genes,
+            self.weights.chunks(row_length),
+            coefficient_rows
+        )
+        .enumerate()
+        .flat_map(
+            move |(neuron_index, (bias, delay, activation, row, coefficients))| {
+                // Dense rows skip their zero self-connection.
+                let skip_at = if dense { neuron_index } else { row.len() };
+                let (before, after) = row.split_at(skip_at);
+                let (coefficients_before, coefficients_after) =
+                    coefficients.split_at((skip_at * HEBBIAN_COEFFICIENTS).min(coefficients.len()));
+                once((GeneKind::Bias, bias))
+                    .chain(once((GeneKind::Delay, delay)))
+                    .chain(
+                        once((GeneKind::Activation, activation)).filter(move |_| activation_genes),
+                    )
+                    .chain(
+                        before
+                            .iter()
+                            .chain(after.iter().skip(dense as usize))
+                            .map(|weight| (GeneKind::Weight, weight)),
+                    )
+                    .chain(
+                        coefficients_before
+                            .iter()
+                            .chain(
+                                coefficients_after
+                                    .iter()
+                                    .skip(dense as usize * HEBBIAN_COEFFICIENTS),
+                            )
+                            .map(|coefficient| (GeneKind::Plasticity, coefficient)),
+                    )
+            },
+        )
+        .chain(
+            self.readout_weights
+                .iter()
+                .map(|weight| (GeneKind::Readout, weight)),
+        )
+        .chain(
+            self.step_sizes
+                .iter()
+                .map(|step_size| (GeneKind::StepSize, step_size)),
+        )
+    }
 
-        let bias = self.bias(neuron_index);
+    /// Mutable access to every gene, in the same order as [`ThinkingLayer::genome`].
+    ///
+    /// Plastic layers need [`ThinkingLayer::reset_plastic_weights`] afterwards to pick up
+    /// changed weights.
+    pub fn genes_mut(&mut self) -> impl Iterator<Item = &mut F> {
+        self.classified_genes_mut().map(|(_, gene)| gene)
+    }
+
+    /// Mutable access to every gene together with what it controls.
+    pub fn classified_genes_mut(&mut self) -> impl Iterator<Item = (GeneKind, &mut F)> {
+        let dense = self.settings.connectivity.is_dense();
+        let activation_genes = self.settings.has_activation_genes();
+        let row_length = self.row_length().max(1);
+        let coefficient_rows = if self.settings.plasticity.is_plastic() {
+            Either::Left(
+                self.hebbian_coefficients
+                    .chunks_mut(row_length * HEBBIAN_COEFFICIENTS),
+            )
+        } else {
+            Either::Right(repeat_with(<&mut [F]>::default))
+        };
+
+        izip!(
+            self.biases.iter_mut(),
+            self.delays.iter_mut(),
+            self.activation_genes.iter_mut(),
+            self.weights.chunks_mut(row_length),
+            coefficient_rows
+        )
+        .enumerate()
+        .flat_map(
+            move |(neuron_index, (bias, delay, activation, row, coefficients))| {
+                let skip_at = if dense { neuron_index } else { row.len() };
+                let (before, after) = row.split_at_mut(skip_at);
+                let split_at = (skip_at * HEBBIAN_COEFFICIENTS).min(coefficients.len());
+                let (coefficients_before, coefficients_after) = coefficients.split_at_mut(split_at);
+                once((GeneKind::Bias, bias))
+                    .chain(once((GeneKind::Delay, delay)))
+                    .chain(
+                        once((GeneKind::Activation, activation)).filter(move |_| activation_genes),
+                    )
+                    .chain(
+                        before
+                            .iter_mut()
+                            .chain(after.iter_mut().skip(dense as usize))
+                            .map(|weight| (GeneKind::Weight, weight)),
+                    )
+                    .chain(
+                        coefficients_before
+                            .iter_mut()
+                            .chain(
+                                coefficients_after
+                                    .iter_mut()
+                                    .skip(dense as usize * HEBBIAN_COEFFICIENTS),
+                            )
+                            .map(|coefficient| (GeneKind::Plasticity, coefficient)),
+                    )
+            },
+        )
+        .chain(
+            self.readout_weights
+                .iter_mut()
+                .map(|weight| (GeneKind::Readout, weight)),
+        )
+        .chain(
+            self.step_sizes
+                .iter_mut()
+                .map(|step_size| (GeneKind::StepSize, step_size)),
+        )
+    }
 
-        self.activation_function.apply(sum + bias)
+    fn scatter_genome(&mut self, genome: Vec<F>) {
+        self.genes_mut()
+            .zip(genome)
+            .for_each(|(gene, value)| *gene = value);
+        self.reset_plastic_weights();
     }
 
-    pub fn genome(&self) -> &[f64] {
-        &self.genome
+    /// Replaces the genome after checking it fits this layer.
+    pub fn set_genome(&mut self, genome: Vec<F>) -> Result<(), GenomeError> {
+        Self::validate_genome(
+            self.internal_size,
+            self.output_size,
+            &self.settings,
+            &genome,
+        )?;
+        self.scatter_genome(genome);
+        Ok(())
     }
 
-    pub fn genome_mut(&mut self) -> &mut [f64] {
-        &mut self.genome
+    /// Checks that `genome` can drive a layer with `internal_size` neurons and `settings`.
+    pub fn validate_genome(
+        internal_size: usize,
+        output_size: usize,
+        settings: &LayerSettings,
+        genome: &[F],
+    ) -> Result<(), GenomeError> {
+        let expected = Self::genome_length_for(internal_size, output_size, settings);
+        if expected != Some(genome.len()) {
+            return Err(GenomeError::LengthMismatch {
+                // No genome in memory can be longer than one that does not fit in a usize.
+                expected: expected.unwrap_or(usize::MAX),
+                actual: genome.len(),
+            });
+        }
+
+        if let Some((index, value)) = genome
+            .iter()
+            .enumerate()
+            .find(|(_, gene)| !gene.is_finite())
+        {
+            return Err(GenomeError::NonFiniteGene {
+                index,
+                value: value.into_f64(),
+            });
+        }
+
+        let neuron_data_length = neuron_data_length_for(internal_size, settings);
+        if let Some((neuron, delay)) = genome
+            .iter()
+            .skip(1)
+            .step_by(neuron_data_length)
+            .take(internal_size)
+            .enumerate()
+            .find(|(_, delay)| !(MIN_DELAY..=MAX_DELAY).contains(&delay.into_f64()))
+        {
+            return Err(GenomeError::DelayOutOfRange {
+                neuron,
+                delay: delay.into_f64(),
+            });
+        }
+
+        let step_sizes_start = genome.len() - settings.self_adaptation.step_size_count();
+        if let Some((index, step_size)) = genome[step_sizes_start..]
+            .iter()
+            .enumerate()
+            .find(|(_, step_size)| **step_size <= F::zero())
+        {
+            return Err(GenomeError::StepSizeOutOfRange {
+                index,
+                step_size: step_size.into_f64(),
+            });
+        }
+
+        Ok(())
     }
 
-    pub fn set_genome(&mut self, genome: Vec<f64>) {
-        self.genome = genome;
+    /// Checks that `sources` describe valid connections for a layer with `settings`.
+    pub fn validate_sources(
+        internal_size: usize,
+        settings: &LayerSettings,
+        sources: &[u32],
+    ) -> anyhow::Result<()> {
+        if settings.connectivity.is_dense() {
+            if !sources.is_empty() {
+                bail!("Dense thinking layers cannot have explicit sources")
+            }
+            return Ok(());
+        }
+
+        let fan_in = settings.connectivity.fan_in(internal_size);
+        if internal_size.checked_mul(fan_in) != Some(sources.len()) {
+            bail!(
+                "Expected {} neurons with a fan-in of {} each but got {} sources",
+                internal_size,
+                fan_in,
+                sources.len()
+            )
+        }
+
+        for (neuron_index, row) in sources.chunks(fan_in.max(1)).enumerate() {
+            if let Some(source) = row.iter().find(|source| {
+                **source as usize >= internal_size || **source as usize == neuron_index
+            }) {
+                bail!(
+                    "Neuron {} cannot receive input from neuron {}",
+                    neuron_index,
+                    source
+                )
+            }
+        }
+
+        Ok(())
     }
 
     pub fn activation_function(&self) -> &ActivationFunction {
         &self.activation_function
     }
 }
+
+fn row_length_for(internal_size: usize, settings: &LayerSettings) -> usize {
+    match settings.connectivity {
+        Connectivity::Dense => internal_size,
+        connectivity => connectivity.fan_in(internal_size),
+    }
+}
+
+fn genes_per_connection(settings: &LayerSettings) -> usize {
+    match settings.plasticity {
+        Plasticity::Static => 1,
+        Plasticity::Hebbian => 1 + HEBBIAN_COEFFICIENTS,
+    }
+}
+
+fn neuron_data_length_for(internal_size: usize, settings: &LayerSettings) -> usize {
+    2 + settings.has_activation_genes() as usize
+        + settings.connectivity.fan_in(internal_size) * genes_per_connection(settings)
+}
+
+/// Moves `delays` into `MIN_DELAY..=MAX_DELAY`, which does not change how the layer ticks.
+pub(crate) fn clamp_delays<'a, F: Float>(delays: impl IntoIterator<Item = &'a mut F>) {
+    let (min, max) = (F::from_f64(MIN_DELAY), F::from_f64(MAX_DELAY));
+    for delay in delays {
+        *delay = delay.max(min).min(max);
+    }
+}
+
+/// [`clamp_delays`] on the delay genes of a genome laid out like [`ThinkingLayer::genome`].
+pub(crate) fn clamp_delay_genes<F: Float>(
+    genome: &mut [F],
+    internal_size: usize,
+    settings: &LayerSettings,
+) {
+    clamp_delays(
+        genome
+            .iter_mut()
+            .skip(1)
+            .step_by(neuron_data_length_for(internal_size, settings))
+            .take(internal_size),
+    );
+}
+
+/// Number of steps and the update mode to step with to advance by `duration` units of model
+/// time, see [`ThinkingLayer::tick_for`].
+pub(crate) fn steps_for(update_mode: UpdateMode, duration: f64) -> (usize, UpdateMode) {
+    assert!(
+        duration.is_finite() && duration > 0.0,
+        "Duration must be positive and finite, got {}",
+        duration
+    );
+    let UpdateMode::Continuous { integrator, dt } = update_mode else {
+        return (1, update_mode);
+    };
+
+    let steps = (duration / dt).ceil().max(1.0);
+    (
+        steps as usize,
+        UpdateMode::Continuous {
+            integrator,
+            dt: duration / steps,
+        },
+    )
+}
+
+/// Writes `input` into the input neuron states or currents, depending on `input_encoding`.
+pub(crate) fn write_input<F: Float>(
+    input_encoding: InputEncoding,
+    input: &[F],
+    input_states: &mut [F],
+    input_current: &mut [F],
+) {
+    match input_encoding {
+        InputEncoding::Clamped => {
+            for (state, value) in input_states.iter_mut().zip(input) {
+                *state = *value;
+            }
+        }
+        InputEncoding::Scaled { scale, offset } => {
+            let (scale, offset) = (F::from_f64(scale), F::from_f64(offset));
+            for (state, value) in input_states.iter_mut().zip(input) {
+                *state = *value * scale + offset;
+            }
+        }
+        InputEncoding::Additive => {
+            for (current, value) in input_current.iter_mut().zip(input) {
+                *current = *value;
+            }
+        }
+    }
+}
+
+/// Number of read-out genes, a bias and one weight per neuron for every output of a linear
+/// read-out.
+fn readout_length_for(internal_size: usize, output_size: usize, settings: &LayerSettings) -> usize {
+    match settings.output_readout {
+        OutputReadout::Linear => output_size * (internal_size + 1),
+        OutputReadout::Raw | OutputReadout::Softmax => 0,
+    }
+}
+
+/// Picks the source neurons of every neuron of a sparse layer, sorted per neuron.
+fn build_sources<R: Rng + ?Sized>(
+    connectivity: Connectivity,
+    internal_size: usize,
+    rng: &mut R,
+) -> Vec<u32> {
+    match connectivity {
+        Connectivity::Dense => Vec::new(),
+        Connectivity::Random { fan_in } => (0..internal_size)
+            .flat_map(|neuron_index| {
+                let mut row: Vec<u32> = index::sample(rng, internal_size - 1, fan_in)
+                    .into_iter()
+                    // Skip over the neuron itself
+                    .map(|source| (source + (source >= neuron_index) as usize) as u32)
+                    .collect();
+                row.sort_unstable();
+                row
+            })
+            .collect(),
+        Connectivity::Local { fan_in } => (0..internal_size)
+            .flat_map(|neuron_index| {
+                let mut row: Vec<u32> = (1..)
+                    .flat_map(|distance: isize| [distance, -distance])
+                    .take(fan_in)
+                    .map(|offset| {
+                        (neuron_index as isize + offset).rem_euclid(internal_size as isize) as u32
+                    })
+                    .collect();
+                row.sort_unstable();
+                row
+            })
+            .collect(),
+    }
+}
+
+/// Parameters of one layer borrowed from wherever they are stored.
+///
+/// Shared by [`ThinkingLayer`] and [`ThinkingLayerBatch`](crate::thinking_layer_batch::ThinkingLayerBatch)
+/// so both tick exactly the same way. One set of parameters can drive several members at once,
+/// each with its own states, which then share every weight row.
+pub(crate) struct LayerParameters<'a, F: Float> {
+    /// Leading neurons that are written from outside and never updated.
+    pub clamped_inputs: usize,
+    /// Added to the weighted sums of the first neurons, see [`InputEncoding::Additive`]. The
+    /// same number of values per member, one member after another.
+    pub input_current: &'a [F],
+    pub activation_function: &'a ActivationFunction,
+    pub weights: &'a [F],
+    pub sources: &'a [u32],
+    pub biases: &'a [F],
+    pub delays: &'a [F],
+    pub activation_genes: &'a [F],
+    pub activation_palette: &'a [ActivationFunction],
+    pub update_mode: UpdateMode,
+}
+
+impl<F: Float> LayerParameters<'_, F> {
+    /// Advances the states of every member by one tick, the inputs must already be written
+    /// into them.
+    ///
+    /// `neuron_states` holds the states of one member after another, `internal_ticks` the tick
+    /// counter of every member and `scratch` needs [`scratch_length_for`] values per member.
+    pub fn step(&self, neuron_states: &mut [F], scratch: &mut [F], internal_ticks: &mut [usize]) {
+        let internal_size = self.biases.len();
+        let members = internal_ticks.len();
+        if internal_size == 0 || members == 0 {
+            return;
+        }
+
+        for internal_tick in internal_ticks.iter_mut().filter(|tick| **tick == 0) {
+            *internal_tick = 1;
+        }
+
+        if let UpdateMode::Continuous {
+            integrator: Integrator::RungeKutta4,
+            dt,
+        } = self.update_mode
+        {
+            self.runge_kutta_step(neuron_states, scratch, F::from_f64(dt));
+        } else {
+            let activations = &mut scratch[..internal_size * members];
+            let ticks: &[usize] = internal_ticks;
+
+            // Masked matrix product: only neurons with an update rate this tick in some member
+            // are recomputed, all others keep their previous state.
+            self.activate_all(neuron_states, activations, |neuron_index| {
+                ticks.iter().all(|tick| {
+                    update_rate(self.update_mode, self.delays[neuron_index], *tick).is_none()
+                })
+            });
+
+            for (member, (states, tick)) in neuron_states
+                .chunks_exact_mut(internal_size)
+                .zip(ticks)
+                .enumerate()
+            {
+                for neuron_index in self.clamped_inputs..internal_size {
+                    if let Some(rate) =
+                        update_rate(self.update_mode, self.delays[neuron_index], *tick)
+                    {
+                        let activation = activations[neuron_index * members + member];
+                        states[neuron_index] = blend(states[neuron_index], activation, rate);
+                    }
+                }
+            }
+        }
+
+        for internal_tick in internal_ticks {
+            *internal_tick = internal_tick.overflowing_add(1).0;
+        }
+    }
+
+    /// One fourth order Runge-Kutta step, the input neurons are held constant.
+    fn runge_kutta_step(&self, neuron_states: &mut [F], scratch: &mut [F], dt: F) {
+        let internal_size = self.biases.len();
+        let length = neuron_states.len();
+        let members = length / internal_size;
+        let (probe, rest) = scratch.split_at_mut(length);
+        let (activations, weighted_sum) = rest.split_at_mut(length);
+        let weighted_sum = &mut weighted_sum[..length];
+        let half_dt = dt / F::from_f64(2.0);
+        let two = F::from_f64(2.0);
+
+        probe.copy_from_slice(neuron_states);
+        weighted_sum.fill(F::zero());
+
+        // Every stage evaluates the slope at the probe left by the previous one and moves the
+        // probe to where the next stage evaluates it.
+        for (weight, probe_step) in [
+            (F::one(), half_dt),
+            (two, half_dt),
+            (two, dt),
+            (F::one(), F::zero()),
+        ] {
+            self.activate_all(probe, activations, |_| false);
+
+            for member in 0..members {
+                for neuron_index in self.clamped_inputs..internal_size {
+                    let index = member * internal_size + neuron_index;
+                    let slope = (activations[neuron_index * members + member] - probe[index])
+                        / time_constant(self.delays[neuron_index], dt);
+                    weighted_sum[index] = weighted_sum[index] + weight * slope;
+                    probe[index] = neuron_states[index] + probe_step * slope;
+                }
+            }
+        }
+
+        let sixth_dt = dt / F::from_f64(6.0);
+        for (index, state) in neuron_states.iter_mut().enumerate() {
+            if index % internal_size >= self.clamped_inputs {
+                *state = *state + sixth_dt * weighted_sum[index];
+            }
+        }
+    }
+
+    /// Writes the activation of every non-input neuron of every member for `neuron_states`
+    /// into `activations`, neuron by neuron: `activations[neuron * members + member]`.
+    ///
+    /// Every weight row is loaded once and applied to the states of all members, which makes
+    /// this a matrix product of the weights and the member states. Neurons for which `skip`
+    /// holds are left out. Large layers split their neurons across threads when the `parallel`
+    /// feature is enabled.
+    fn activate_all(
+        &self,
+        neuron_states: &[F],
+        activations: &mut [F],
+        skip: impl Fn(usize) -> bool + Sync,
+    ) {
+        let internal_size = self.biases.len();
+        let members = neuron_states.len() / internal_size;
+        let currents_per_member = self.input_current.len() / members;
+        let row_length = self.weights.len() / internal_size;
+
+        let activate = |(offset, targets): (usize, &mut [F])| {
+            let neuron_index = offset + self.clamped_inputs;
+            if skip(neuron_index) {
+                return;
+            }
+
+            let start = row_length * neuron_index;
+            let weights = &self.weights[start..start + row_length];
+            let activation_function = neuron_activation_function(
+                self.activation_function,
+                self.activation_palette,
+                self.activation_genes[neuron_index],
+            );
+
+            for (member, (target, states)) in targets
+                .iter_mut()
+                .zip(neuron_states.chunks_exact(internal_size))
+                .enumerate()
+            {
+                let sum = if self.sources.is_empty() {
+                    kernel::dot(weights, states)
+                } else {
+                    kernel::sparse_dot(weights, &self.sources[start..start + row_length], states)
+                };
+                let current = if neuron_index < currents_per_member {
+                    self.input_current[member * currents_per_member + neuron_index]
+                } else {
+                    F::zero()
+                };
+
+                *target = activation_function.apply(sum + self.biases[neuron_index] + current);
+            }
+        };
+
+        let targets = &mut activations[self.clamped_inputs * members..internal_size * members];
+
+        #[cfg(feature = "parallel")]
+        if internal_size >= PARALLEL_TICK_THRESHOLD {
+            use rayon::prelude::*;
+
+            targets
+                .par_chunks_exact_mut(members)
+                .enumerate()
+                .for_each(activate);
+        } else {
+            targets
+                .chunks_exact_mut(members)
+                .enumerate()
+                .for_each(activate);
+        }
+
+        #[cfg(not(feature = "parallel"))]
+        targets
+            .chunks_exact_mut(members)
+            .enumerate()
+            .for_each(activate);
+    }
+}
+
+/// Activation function picked by an activation gene, the layer's own one without a palette.
+fn neuron_activation_function<'a, F: Float>(
+    activation_function: &'a ActivationFunction,
+    activation_palette: &'a [ActivationFunction],
+    activation_gene: F,
+) -> &'a ActivationFunction {
+    if activation_palette.is_empty() {
+        return activation_function;
+    }
+
+    let index = activation_gene
+        .round()
+        .max(F::zero())
+        .to_usize()
+        .unwrap_or(usize::MAX);
+    &activation_palette[index.min(activation_palette.len() - 1)]
+}
+
+/// Fraction of the way a neuron moves towards its new activation on `tick`, `None` when it keeps
+/// its state. Continuous layers move by one Euler step.
+pub(crate) fn update_rate<F: Float>(update_mode: UpdateMode, delay: F, tick: usize) -> Option<F> {
+    match update_mode {
+        UpdateMode::Gated => tick.is_multiple_of(delay_ticks(delay)).then(F::one),
+        UpdateMode::LeakyIntegrator => Some(F::one() / delay.max(F::one())),
+        UpdateMode::Continuous { dt, .. } => {
+            let dt = F::from_f64(dt);
+            Some(dt / time_constant(delay, dt))
+        }
+    }
+}
+
+/// Time constant of a continuous-time neuron, never below `dt` so Euler steps cannot overshoot
+/// and never below [`MIN_TIME_CONSTANT`].
+fn time_constant<F: Float>(delay: F, dt: F) -> F {
+    delay.max(dt).max(F::from_f64(MIN_TIME_CONSTANT))
+}
+
+/// Number of scratch values [`LayerParameters::step`] needs per member, Runge-Kutta keeps
+/// intermediate states and slopes around.
+pub(crate) fn scratch_length_for(internal_size: usize, settings: &LayerSettings) -> usize {
+    match settings.update_mode {
+        UpdateMode::Continuous {
+            integrator: Integrator::RungeKutta4,
+            ..
+        } => 3 * internal_size,
+        _ => internal_size,
+    }
+}
+
+/// Moves `state` towards `activation`, landing on it exactly for a rate of one.
+pub(crate) fn blend<F: Float>(state: F, activation: F, rate: F) -> F {
+    if rate == F::one() {
+        activation
+    } else {
+        state + (activation - state) * rate
+    }
+}
+
+/// Number of ticks between two activations of a neuron with the given delay gene.
+fn delay_ticks<F: Float>(delay: F) -> usize {
+    delay.round().max(F::one()).to_usize().unwrap_or(usize::MAX)
+}
+
+fn convert_floats<F: Float, T: Float>(values: &[F]) -> Vec<T> {
+    values
+        .iter()
+        .map(|value| T::from_f64(value.into_f64()))
+        .collect()
+}
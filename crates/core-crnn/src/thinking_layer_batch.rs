@@ -0,0 +1,338 @@
+use crate::activation_function::ActivationFunction;
+use crate::float::Float;
+use crate::layer_settings::{LayerSettings, UpdateMode};
+use crate::thinking_layer::{
+    scratch_length_for, steps_for, write_input, LayerParameters, ThinkingLayer,
+};
+use anyhow::bail;
+use itertools::izip;
+
+/// A population of [`ThinkingLayer`]s sharing one topology, stored as struct-of-arrays.
+///
+/// Every genome can run as several independent samples, which keep their own states but share
+/// one copy of the weights. Each weight row is then applied to the states of all samples at
+/// once, a matrix-matrix product instead of one matrix-vector product per sample. Members are
+/// numbered sample by sample, genome by genome, and are all ticked by a single call.
+#[derive(Debug, Clone)]
+pub struct ThinkingLayerBatch<F: Float = f64> {
+    input_size: usize,
+    internal_size: usize,
+    output_size: usize,
+
+    activation_function: ActivationFunction,
+    settings: LayerSettings,
+    /// Members per genome.
+    samples: usize,
+
+    /// The weights of every genome back to back, see [`ThinkingLayer::weights`].
+    weights: Vec<F>,
+    /// Sparse connections are part of the topology, so all genomes share them.
+    sources: Vec<u32>,
+    biases: Vec<F>,
+    delays: Vec<F>,
+    activation_genes: Vec<F>,
+    /// Only carried along so members keep them, see [`ThinkingLayer::step_sizes`].
+    step_sizes: Vec<F>,
+
+    neuron_states: Vec<F>,
+    /// Input currents of additive inputs, `input_size` per member, empty otherwise.
+    input_currents: Vec<F>,
+    next_states: Vec<F>,
+
+    internal_ticks: Vec<usize>,
+}
+
+impl<F: Float> ThinkingLayerBatch<F> {
+    /// Packs `layers` into a batch with one member per layer.
+    ///
+    /// All of them need the same sizes, activation function and connections.
+    pub fn from_layers(layers: &[ThinkingLayer<F>]) -> anyhow::Result<Self> {
+        Self::with_samples(layers, 1)
+    }
+
+    /// Packs `layers` into a batch that runs `samples` members of each, all starting from the
+    /// state of their layer.
+    ///
+    /// All of them need the same sizes, activation function and connections.
+    pub fn with_samples(layers: &[ThinkingLayer<F>], samples: usize) -> anyhow::Result<Self> {
+        let Some(first) = layers.first() else {
+            bail!("Cannot create a batch without any thinking layers")
+        };
+        if samples == 0 {
+            bail!("Cannot create a batch without any samples per thinking layer")
+        }
+
+        let input_size = first.input_size();
+        let internal_size = first.internal_size();
+        let output_size = first.output_size();
+        let activation_function = first.activation_function().clone();
+        let members = layers.len() * samples;
+
+        let mut batch = Self {
+            input_size,
+            internal_size,
+            output_size,
+            activation_function,
+            settings: first.settings().clone(),
+            samples,
+            weights: Vec::with_capacity(layers.len() * first.weights().len()),
+            sources: first.sources().to_vec(),
+            biases: Vec::with_capacity(layers.len() * internal_size),
+            delays: Vec::with_capacity(layers.len() * internal_size),
+            activation_genes: Vec::with_capacity(layers.len() * internal_size),
+            step_sizes: Vec::with_capacity(layers.len() * first.step_sizes().len()),
+            neuron_states: Vec::with_capacity(members * internal_size),
+            input_currents: Vec::with_capacity(members * first.input_current.len()),
+            next_states: vec![
+                F::zero();
+                members * scratch_length_for(internal_size, first.settings())
+            ],
+            internal_ticks: Vec::with_capacity(members),
+        };
+
+        for (index, layer) in layers.iter().enumerate() {
+            if layer.input_size() != input_size
+                || layer.internal_size() != internal_size
+                || layer.output_size() != output_size
+                || *layer.settings() != batch.settings
+                || layer.sources() != batch.sources
+            {
+                bail!(
+                    "Thinking layer {} has a different topology than the rest of the batch",
+                    index
+                )
+            }
+
+            if layer.settings().plasticity.is_plastic() {
+                bail!(
+                    "Thinking layer {} is plastic, which batches do not support",
+                    index
+                )
+            }
+
+            if !layer.settings().output_readout.is_raw() {
+                bail!(
+                    "Thinking layer {} has an output read-out other than raw, which batches do not support",
+                    index
+                )
+            }
+
+            if *layer.activation_function() != batch.activation_function {
+                bail!(
+                    "Thinking layer {} uses a different activation function than the rest of the batch",
+                    index
+                )
+            }
+
+            batch.weights.extend_from_slice(layer.weights());
+            batch.biases.extend_from_slice(layer.biases());
+            batch.delays.extend_from_slice(layer.delays());
+            batch
+                .activation_genes
+                .extend_from_slice(layer.activation_genes());
+            batch.step_sizes.extend_from_slice(layer.step_sizes());
+            for _ in 0..samples {
+                batch.neuron_states.extend_from_slice(layer.neuron_states());
+                batch.input_currents.extend_from_slice(&layer.input_current);
+                batch.internal_ticks.push(layer.internal_tick());
+            }
+        }
+
+        Ok(batch)
+    }
+
+    /// Ticks every member once.
+    ///
+    /// `inputs` holds `input_size` values per member, one member after another.
+    pub fn tick(&mut self, inputs: Option<&[F]>) {
+        if let Some(inputs) = inputs {
+            self.feed(inputs);
+        }
+
+        self.step(self.settings.update_mode);
+    }
+
+    /// Advances every member by `duration` units of model time, see
+    /// [`ThinkingLayer::tick_for`].
+    pub fn tick_for(&mut self, inputs: Option<&[F]>, duration: f64) {
+        let (steps, update_mode) = steps_for(self.settings.update_mode, duration);
+        if let Some(inputs) = inputs {
+            self.feed(inputs);
+        }
+
+        for _ in 0..steps {
+            self.step(update_mode);
+        }
+    }
+
+    fn feed(&mut self, inputs: &[F]) {
+        assert_eq!(
+            inputs.len(),
+            self.len() * self.input_size,
+            "Batch expects {} inputs per member",
+            self.input_size
+        );
+
+        // Chunk sizes of zero would panic, an empty layer simply yields no chunks.
+        let internal_size = self.internal_size.max(1);
+        let input_size = self.input_size.max(1);
+        for (member, (states, input)) in self
+            .neuron_states
+            .chunks_exact_mut(internal_size)
+            .zip(inputs.chunks_exact(input_size))
+            .enumerate()
+        {
+            let current = self.input_currents.get_mut(member * input_size..);
+            write_input(
+                self.settings.input_encoding,
+                input,
+                &mut states[..self.input_size],
+                current.unwrap_or_default(),
+            );
+        }
+    }
+
+    fn step(&mut self, update_mode: UpdateMode) {
+        // Chunk sizes of zero would panic, an empty layer simply yields no chunks.
+        let internal_size = self.internal_size.max(1);
+        let clamped_inputs = if self.settings.input_encoding.is_clamped() {
+            self.input_size
+        } else {
+            0
+        };
+        let row_length = self.weights.len() / self.genome_count() / internal_size;
+        let currents_per_genome = self.samples * self.input_size;
+        let scratch_length = scratch_length_for(internal_size, &self.settings);
+
+        for (genome, (weights, biases, delays, activation_genes, states, next_states, ticks)) in
+            izip!(
+                self.weights
+                    .chunks_exact((internal_size * row_length).max(1)),
+                self.biases.chunks_exact(internal_size),
+                self.delays.chunks_exact(internal_size),
+                self.activation_genes.chunks_exact(internal_size),
+                self.neuron_states
+                    .chunks_exact_mut(self.samples * internal_size),
+                self.next_states
+                    .chunks_exact_mut(self.samples * scratch_length),
+                self.internal_ticks.chunks_exact_mut(self.samples)
+            )
+            .enumerate()
+        {
+            LayerParameters {
+                clamped_inputs,
+                input_current: self
+                    .input_currents
+                    .get(genome * currents_per_genome..(genome + 1) * currents_per_genome)
+                    .unwrap_or_default(),
+                activation_function: &self.activation_function,
+                weights,
+                sources: &self.sources,
+                biases,
+                delays,
+                activation_genes,
+                activation_palette: &self.settings.activation_palette,
+                update_mode,
+            }
+            .step(states, next_states, ticks);
+        }
+    }
+
+    /// Clears the neuron states and tick counters of every member, see
+    /// [`ThinkingLayer::reset_state`].
+    pub fn reset_states(&mut self) {
+        self.neuron_states.fill(F::zero());
+        self.input_currents.fill(F::zero());
+        self.internal_ticks.fill(1);
+    }
+
+    /// Number of members, `samples` per genome.
+    pub fn len(&self) -> usize {
+        self.internal_ticks.len()
+    }
+
+    pub fn genome_count(&self) -> usize {
+        self.len() / self.samples
+    }
+
+    /// Members per genome.
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.internal_ticks.is_empty()
+    }
+
+    pub fn input_size(&self) -> usize {
+        self.input_size
+    }
+
+    pub fn internal_size(&self) -> usize {
+        self.internal_size
+    }
+
+    pub fn output_size(&self) -> usize {
+        self.output_size
+    }
+
+    pub fn neuron_states(&self, member: usize) -> &[F] {
+        let start = member * self.internal_size;
+        &self.neuron_states[start..start + self.internal_size]
+    }
+
+    pub fn output(&self, member: usize) -> &[F] {
+        &self.neuron_states(member)[self.internal_size - self.output_size..]
+    }
+
+    pub fn outputs(&self) -> impl Iterator<Item = &[F]> {
+        (0..self.len()).map(|member| self.output(member))
+    }
+
+    /// Copies a single member back out into a standalone layer.
+    pub fn layer(&self, member: usize) -> ThinkingLayer<F> {
+        let genome = member / self.samples;
+        let mut layer = ThinkingLayer::empty(
+            self.input_size,
+            self.internal_size,
+            self.output_size,
+            self.activation_function.clone(),
+            self.settings.clone(),
+            self.sources.clone(),
+        );
+
+        let weights_per_genome = layer.weights.len();
+        let start = genome * self.internal_size;
+        let range = start..start + self.internal_size;
+
+        layer.weights.copy_from_slice(
+            &self.weights[genome * weights_per_genome..(genome + 1) * weights_per_genome],
+        );
+        layer.biases.copy_from_slice(&self.biases[range.clone()]);
+        layer.delays.copy_from_slice(&self.delays[range.clone()]);
+        layer
+            .activation_genes
+            .copy_from_slice(&self.activation_genes[range]);
+        layer
+            .neuron_states
+            .copy_from_slice(self.neuron_states(member));
+        let step_size_count = layer.step_sizes.len();
+        layer.step_sizes.copy_from_slice(
+            &self.step_sizes[genome * step_size_count..(genome + 1) * step_size_count],
+        );
+        let input_currents = &self.input_currents;
+        if !input_currents.is_empty() {
+            let start = member * self.input_size;
+            layer
+                .input_current
+                .copy_from_slice(&input_currents[start..start + self.input_size]);
+        }
+        layer.internal_tick = self.internal_ticks[member];
+
+        layer
+    }
+
+    pub fn into_layers(self) -> Vec<ThinkingLayer<F>> {
+        (0..self.len()).map(|member| self.layer(member)).collect()
+    }
+}
@@ -0,0 +1,101 @@
+use core_crnn::activation_function::{
+    register_activation_function, register_activation_function_with_derivative, ActivationFunction,
+};
+use core_crnn::persisted_model::{ModelMetadata, PersistedModel};
+use core_crnn::thinking_layer::ThinkingLayer;
+
+#[test]
+fn parameterized_functions_use_their_parameters() {
+    assert_eq!(
+        ActivationFunction::LeakyRelu { slope: 0.1 }.apply(-2.0),
+        -0.2
+    );
+    assert_eq!(ActivationFunction::Elu { alpha: 2.0 }.apply(3.0), 3.0);
+    assert!((ActivationFunction::Elu { alpha: 2.0 }.apply(-1.0f64) + 1.264241).abs() < 1e-6);
+    assert_eq!(ActivationFunction::Softsign.apply(1.0), 0.5);
+    assert_eq!(ActivationFunction::Gaussian.apply(0.0), 1.0);
+    assert_eq!(ActivationFunction::Step.apply(-0.1), 0.0);
+    assert!((ActivationFunction::Gelu.apply(1.0f64) - 0.841192).abs() < 1e-6);
+
+    assert_ne!(
+        ActivationFunction::LeakyRelu { slope: 0.1 },
+        ActivationFunction::LeakyRelu { slope: 0.2 }
+    );
+}
+
+#[test]
+fn custom_functions_are_persisted_by_name() {
+    let cube = register_activation_function("cube", |x| x * x * x);
+    assert_eq!(ActivationFunction::custom("cube").unwrap(), cube);
+    assert_eq!(cube.apply(2.0), 8.0);
+
+    let layer: ThinkingLayer = ThinkingLayer::new(2, 10, 2, cube.clone()).unwrap();
+    let json = PersistedModel::from_layer(&layer, ModelMetadata::default())
+        .unwrap()
+        .to_json()
+        .unwrap();
+    let restored: ThinkingLayer = PersistedModel::from_json(json.as_bytes())
+        .unwrap()
+        .into_layer()
+        .unwrap();
+
+    assert_eq!(*restored.activation_function(), cube);
+}
+
+#[test]
+fn unregistered_custom_functions_fail_to_load() {
+    let layer: ThinkingLayer =
+        ThinkingLayer::new(2, 10, 2, register_activation_function("halve", |x| x / 2.0)).unwrap();
+    let json = PersistedModel::from_layer(&layer, ModelMetadata::default())
+        .unwrap()
+        .to_json()
+        .unwrap()
+        .replace("halve", "unknown");
+
+    assert!(PersistedModel::from_json(json.as_bytes()).is_err());
+    assert!(ActivationFunction::custom("unknown").is_err());
+}
+
+#[test]
+fn derivatives_match_finite_differences() {
+    let functions = [
+        ActivationFunction::Tanh,
+        ActivationFunction::Sigmoid,
+        ActivationFunction::Relu,
+        ActivationFunction::LeakyRelu { slope: 0.1 },
+        ActivationFunction::Elu { alpha: 1.5 },
+        ActivationFunction::Gelu,
+        ActivationFunction::Softsign,
+        ActivationFunction::Sine,
+        ActivationFunction::Gaussian,
+        ActivationFunction::Step,
+        ActivationFunction::Identity,
+        register_activation_function("square", |x| x * x),
+    ];
+
+    for function in functions {
+        for x in [-2.0f64, -0.7, 0.3, 1.9] {
+            let step = 1e-6;
+            let numerical = (function.apply(x + step) - function.apply(x - step)) / (2.0 * step);
+            assert!(
+                (function.derivative(x) - numerical).abs() < 1e-5,
+                "{:?} at {}",
+                function,
+                x
+            );
+        }
+    }
+}
+
+#[test]
+fn supplied_derivatives_are_used() {
+    // Deliberately wrong to tell it apart from the numerical fallback.
+    let function = register_activation_function_with_derivative("double", |x| 2.0 * x, |_| 3.0);
+    assert_eq!(function.derivative(1.0f64), 3.0);
+    assert_eq!(
+        ActivationFunction::custom("double")
+            .unwrap()
+            .derivative(1.0f64),
+        3.0
+    );
+}
@@ -0,0 +1,85 @@
+use core_crnn::activation_function::ActivationFunction;
+use core_crnn::genome::{GeneKind, Genome};
+use core_crnn::layer_settings::LayerSettings;
+use core_crnn::mutation::Mutation;
+use core_crnn::persisted_model::{BinaryPrecision, ModelMetadata, PersistedModel};
+use core_crnn::thinking_layer::ThinkingLayer;
+use rand::rng;
+
+fn palette_layer() -> ThinkingLayer {
+    ThinkingLayer::with_settings(
+        2,
+        20,
+        2,
+        ActivationFunction::Tanh,
+        LayerSettings::default().activation_palette(vec![
+            ActivationFunction::Tanh,
+            ActivationFunction::Relu,
+            ActivationFunction::Sigmoid,
+        ]),
+    )
+    .unwrap()
+}
+
+#[test]
+fn every_neuron_carries_an_activation_gene() {
+    let mut layer = palette_layer();
+    assert_eq!(layer.genome().len(), 20 * (3 + 19));
+
+    let kinds: Vec<_> = layer
+        .classified_genes()
+        .take(4)
+        .map(|(kind, _)| kind)
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            GeneKind::Bias,
+            GeneKind::Delay,
+            GeneKind::Activation,
+            GeneKind::Weight
+        ]
+    );
+
+    layer.mutate(&Mutation::gaussian(1.0, 0.1).unwrap(), &mut rng());
+    for (_, gene) in layer
+        .classified_genes()
+        .filter(|(kind, _)| *kind == GeneKind::Activation)
+    {
+        assert!([0.0, 1.0, 2.0].contains(gene));
+    }
+}
+
+#[test]
+fn activation_gene_selects_the_neuron_activation_function() {
+    let mut layer = palette_layer();
+    let mut genome = layer.genome();
+    // Relu for neuron 0, out of range values clamp to the last palette entry.
+    genome[2] = 1.0;
+    genome[22 + 2] = 7.0;
+    layer.set_genome(genome).unwrap();
+
+    assert_eq!(
+        *layer.neuron_activation_function(0),
+        ActivationFunction::Relu
+    );
+    assert_eq!(
+        *layer.neuron_activation_function(1),
+        ActivationFunction::Sigmoid
+    );
+}
+
+#[test]
+fn activation_genes_round_trip_through_model_file() {
+    let layer = palette_layer();
+    let model = PersistedModel::from_layer(&layer, ModelMetadata::default()).unwrap();
+
+    let restored: ThinkingLayer =
+        PersistedModel::from_binary(&model.to_binary(BinaryPrecision::F64).unwrap())
+            .unwrap()
+            .into_layer()
+            .unwrap();
+
+    assert_eq!(restored.settings(), layer.settings());
+    assert_eq!(restored.activation_genes(), layer.activation_genes());
+}
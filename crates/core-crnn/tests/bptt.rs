@@ -0,0 +1,120 @@
+use core_crnn::activation_function::ActivationFunction;
+use core_crnn::bptt::{BpttTrainer, Optimizer, SequenceStep};
+use core_crnn::genome::GeneKind;
+use core_crnn::layer_settings::{
+    Connectivity, InputEncoding, Integrator, LayerSettings, UpdateMode,
+};
+use core_crnn::thinking_layer::ThinkingLayer;
+
+fn sequence() -> Vec<SequenceStep> {
+    (0..6)
+        .map(|step| {
+            let x = step as f64 / 3.0 - 1.0;
+            SequenceStep::new(vec![x, -x], vec![x * 0.5, 0.25])
+        })
+        .collect()
+}
+
+/// Loss after replacing gene `index` of the genome.
+fn loss_with_gene(layer: &ThinkingLayer, index: usize, value: f64) -> f64 {
+    let mut layer = layer.clone();
+    let mut genome = layer.genome();
+    genome[index] = value;
+    layer.set_genome(genome).unwrap();
+    BpttTrainer::gradients(&layer, &sequence()).unwrap().loss
+}
+
+#[test]
+fn gradients_match_finite_differences() {
+    for settings in [
+        LayerSettings::default(),
+        LayerSettings::default().connectivity(Connectivity::Local { fan_in: 3 }),
+        LayerSettings::default().update_mode(UpdateMode::LeakyIntegrator),
+        LayerSettings::default().update_mode(UpdateMode::Continuous {
+            integrator: Integrator::Euler,
+            dt: 0.5,
+        }),
+        LayerSettings::default().input_encoding(InputEncoding::Scaled {
+            scale: 2.0,
+            offset: -0.5,
+        }),
+        LayerSettings::default().input_encoding(InputEncoding::Additive),
+    ] {
+        let mut layer: ThinkingLayer =
+            ThinkingLayer::with_settings(2, 6, 2, ActivationFunction::Tanh, settings).unwrap();
+        // Mix delays of one and two so the mask actually gates or slows down some neurons.
+        let mut genome = layer.genome();
+        for (neuron_index, gene) in layer
+            .classified_genes()
+            .enumerate()
+            .filter(|(_, (kind, _))| *kind == GeneKind::Delay)
+            .map(|(index, _)| index)
+            .enumerate()
+        {
+            genome[gene] = 1.0 + (neuron_index % 2) as f64 * 1.5;
+        }
+        layer.set_genome(genome.clone()).unwrap();
+
+        // Analytic gradients in genome order, `None` for the untrained delays.
+        let gradients = BpttTrainer::gradients(&layer, &sequence()).unwrap();
+        let dense = layer.settings().connectivity.is_dense();
+        let row_length = layer.row_length();
+        let analytic = (0..layer.internal_size()).flat_map(|neuron_index| {
+            let row = &gradients.weights[neuron_index * row_length..][..row_length];
+            [Some(gradients.biases[neuron_index]), None]
+                .into_iter()
+                .chain(
+                    row.iter()
+                        .enumerate()
+                        .filter(move |(source, _)| !dense || *source != neuron_index)
+                        .map(|(_, gradient)| Some(*gradient)),
+                )
+        });
+
+        for (index, analytic) in analytic.enumerate() {
+            let Some(analytic) = analytic else {
+                continue;
+            };
+
+            let step = 1e-6;
+            let numerical = (loss_with_gene(&layer, index, genome[index] + step)
+                - loss_with_gene(&layer, index, genome[index] - step))
+                / (2.0 * step);
+            assert!(
+                (analytic - numerical).abs() < 1e-6,
+                "gene {}: {} vs {}",
+                index,
+                analytic,
+                numerical
+            );
+        }
+    }
+}
+
+#[test]
+fn training_reduces_the_loss() {
+    for optimizer in [Optimizer::sgd(0.1), Optimizer::adam(0.01)] {
+        let mut layer: ThinkingLayer =
+            ThinkingLayer::new(2, 8, 2, ActivationFunction::Tanh).unwrap();
+        let genome: Vec<f64> = layer
+            .classified_genes()
+            .map(|(kind, gene)| if kind == GeneKind::Delay { 1.0 } else { *gene })
+            .collect();
+        layer.set_genome(genome).unwrap();
+
+        let states = layer.neuron_states().to_vec();
+        let mut trainer = BpttTrainer::new(optimizer).gradient_clip(1.0);
+        let initial_loss = BpttTrainer::gradients(&layer, &sequence()).unwrap().loss;
+        trainer.train(&mut layer, &[sequence()], 200).unwrap();
+        let final_loss = BpttTrainer::gradients(&layer, &sequence()).unwrap().loss;
+
+        assert!(
+            final_loss < initial_loss / 4.0,
+            "{:?}: {} -> {}",
+            optimizer,
+            initial_loss,
+            final_loss
+        );
+        assert_eq!(layer.neuron_states(), states);
+    }
+}
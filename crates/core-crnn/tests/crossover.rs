@@ -0,0 +1,232 @@
+use core_crnn::activation_function::ActivationFunction;
+use core_crnn::crossover::Crossover;
+use core_crnn::genome::{GeneId, GeneKind, Genome, GenomeError, NeuronRole};
+use core_crnn::layer_settings::{Connectivity, LayerSettings, OutputReadout, SelfAdaptation};
+use core_crnn::thinking_layer::ThinkingLayer;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::HashMap;
+
+fn parent(seed: u64) -> ThinkingLayer {
+    sized_parent(seed, 2, 8)
+}
+
+fn sized_parent(seed: u64, input_size: usize, internal_size: usize) -> ThinkingLayer {
+    let settings = LayerSettings::default()
+        .activation_palette(vec![
+            ActivationFunction::Tanh,
+            ActivationFunction::Sigmoid,
+            ActivationFunction::Relu,
+        ])
+        .output_readout(OutputReadout::Linear)
+        .self_adaptation(SelfAdaptation::PerGeneClass);
+    ThinkingLayer::with_rng(
+        input_size,
+        internal_size,
+        2,
+        ActivationFunction::Tanh,
+        settings,
+        &mut StdRng::seed_from_u64(seed),
+    )
+    .unwrap()
+}
+
+/// Children of a single pair of parents.
+fn children(
+    parent_a: &ThinkingLayer,
+    parent_b: &ThinkingLayer,
+    crossover: Crossover,
+) -> [Vec<f64>; 2] {
+    let mut children = ThinkingLayer::crossover(
+        parent_a,
+        parent_b,
+        &crossover,
+        1,
+        &mut StdRng::seed_from_u64(7),
+    )
+    .unwrap();
+    let child_b = children.pop().unwrap().genome();
+    let child_a = children.pop().unwrap().genome();
+    [child_a, child_b]
+}
+
+#[test]
+fn swapping_operators_take_every_gene_from_a_parent() {
+    let (parent_a, parent_b) = (parent(1), parent(2));
+    let (genome_a, genome_b) = (parent_a.genome(), parent_b.genome());
+    let blocks = parent_a.gene_blocks();
+    assert_eq!(blocks.len(), genome_a.len());
+
+    assert_eq!(
+        children(&parent_a, &parent_b, Crossover::None),
+        [genome_a.clone(), genome_b.clone()]
+    );
+
+    for crossover in [
+        Crossover::Uniform,
+        Crossover::NPoint { points: 3 },
+        Crossover::NeuronWise,
+    ] {
+        let [child_a, child_b] = children(&parent_a, &parent_b, crossover);
+        let swapped: Vec<_> = (0..genome_a.len())
+            .map(|index| {
+                let genes = (child_a[index], child_b[index]);
+                assert!(
+                    genes == (genome_a[index], genome_b[index])
+                        || genes == (genome_b[index], genome_a[index]),
+                    "{crossover:?} changed gene {index}"
+                );
+                genes.0 != genome_a[index]
+            })
+            .collect();
+        assert!(swapped.contains(&true), "{crossover:?} swapped nothing");
+
+        if crossover == Crossover::NeuronWise {
+            // Genes both parents agree on tell nothing about which parent they came from.
+            for index in (1..genome_a.len()).filter(|index| genome_a[*index] != genome_b[*index]) {
+                let first = (0..index).find(|first| {
+                    blocks[*first] == blocks[index] && genome_a[*first] != genome_b[*first]
+                });
+                if let Some(first) = first {
+                    assert_eq!(
+                        swapped[first], swapped[index],
+                        "Block {} was split",
+                        blocks[index]
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn interpolating_operators_keep_discrete_genes_whole() {
+    let (parent_a, parent_b) = (parent(1), parent(2));
+    let (genome_a, genome_b) = (parent_a.genome(), parent_b.genome());
+    let kinds: Vec<_> = parent_a.classified_genes().map(|(kind, _)| kind).collect();
+
+    for crossover in [
+        Crossover::Blend,
+        Crossover::simulated_binary(1000.0).unwrap(),
+    ] {
+        let [child_a, child_b] = children(&parent_a, &parent_b, crossover);
+        for (index, kind) in kinds.iter().enumerate() {
+            let (a, b) = (genome_a[index], genome_b[index]);
+            if matches!(kind, GeneKind::Activation | GeneKind::StepSize) {
+                assert!([a, b].contains(&child_a[index]) && [a, b].contains(&child_b[index]));
+            } else if matches!(crossover, Crossover::SimulatedBinary(_)) {
+                // A large distribution index keeps children next to their parents.
+                assert!((child_a[index] - a).abs() <= 0.05 * (a - b).abs());
+                assert!((child_b[index] - b).abs() <= 0.05 * (a - b).abs());
+            }
+        }
+    }
+}
+
+#[test]
+fn parents_of_different_sizes_line_up_shared_genes() {
+    let (parent_a, parent_b) = (sized_parent(1, 2, 8), sized_parent(2, 2, 12));
+    let genes_b: HashMap<GeneId, f64> = parent_b
+        .gene_ids()
+        .into_iter()
+        .zip(parent_b.genome())
+        .collect();
+
+    let [child_a, child_b] = children(&parent_a, &parent_b, Crossover::None);
+    assert_eq!([child_a, child_b], [parent_a.genome(), parent_b.genome()]);
+
+    let [child_a, child_b] = children(&parent_a, &parent_b, Crossover::Uniform);
+    assert_eq!(child_b.len(), parent_b.genome_length());
+    let mut from_b = 0;
+    for ((id, gene), own) in parent_a
+        .gene_ids()
+        .into_iter()
+        .zip(child_a)
+        .zip(parent_a.genome())
+    {
+        match genes_b.get(&id) {
+            Some(other) if gene == *other && gene != own => from_b += 1,
+            Some(_) => assert_eq!(gene, own),
+            // Genes only the parent of the child's size has are inherited unchanged.
+            None => assert_eq!(gene, own, "{id:?} changed"),
+        }
+    }
+    assert!(from_b > 0);
+
+    // Output neurons line up even though they sit at different indices.
+    let output_bias = GeneId::Neuron {
+        neuron: NeuronRole::Output(1),
+        kind: GeneKind::Bias,
+    };
+    assert!(parent_a.gene_ids().contains(&output_bias));
+    assert_eq!(parent_b.neuron_role(11), NeuronRole::Output(1));
+}
+
+#[test]
+fn differently_wired_parents_keep_their_connections() {
+    let sparse = |seed| {
+        ThinkingLayer::<f64>::with_rng(
+            2,
+            8,
+            2,
+            ActivationFunction::Tanh,
+            LayerSettings::default().connectivity(Connectivity::Random { fan_in: 3 }),
+            &mut StdRng::seed_from_u64(seed),
+        )
+        .unwrap()
+    };
+    let (parent_a, parent_b) = (sparse(1), sparse(2));
+    assert_ne!(parent_a.sources(), parent_b.sources());
+
+    let children = ThinkingLayer::crossover(
+        &parent_a,
+        &parent_b,
+        &Crossover::None,
+        1,
+        &mut StdRng::seed_from_u64(7),
+    )
+    .unwrap();
+    for (child, parent) in children.iter().zip([&parent_a, &parent_b]) {
+        assert_eq!(child.sources(), parent.sources());
+        assert_eq!(child.genome(), parent.genome());
+    }
+}
+
+#[test]
+fn invalid_distribution_indices_are_rejected() {
+    for distribution_index in [-1.0, -2.0, f64::NAN, f64::INFINITY] {
+        assert!(Crossover::simulated_binary(distribution_index).is_err());
+    }
+
+    let crossover = Crossover::simulated_binary(2.5).unwrap();
+    assert!(matches!(crossover, Crossover::SimulatedBinary(index) if index.get() == 2.5));
+}
+
+#[test]
+fn incompatible_parents_are_rejected() {
+    let parent_a = parent(1);
+    let result = ThinkingLayer::crossover(
+        &parent_a,
+        &sized_parent(2, 3, 8),
+        &Crossover::Blend,
+        1,
+        &mut StdRng::seed_from_u64(7),
+    );
+    assert_eq!(
+        result.unwrap_err(),
+        GenomeError::InterfaceMismatch {
+            input_sizes: [2, 3],
+            output_sizes: [2, 2],
+        }
+    );
+
+    let plain = ThinkingLayer::new(2, 8, 2, ActivationFunction::Tanh).unwrap();
+    let result = ThinkingLayer::crossover(
+        &parent_a,
+        &plain,
+        &Crossover::Blend,
+        1,
+        &mut StdRng::seed_from_u64(7),
+    );
+    assert_eq!(result.unwrap_err(), GenomeError::StructureMismatch);
+}
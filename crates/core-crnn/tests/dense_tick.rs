@@ -0,0 +1,56 @@
+use core_crnn::activation_function::ActivationFunction;
+use core_crnn::thinking_layer::ThinkingLayer;
+
+const INPUT_SIZE: usize = 3;
+const INTERNAL_SIZE: usize = 24;
+
+/// Tick as computed before the weights were stored as a matrix: every neuron walks its part of
+/// the genome, `[bias, delay, weights...]` with the self-connection left out.
+fn per_neuron_tick(genome: &[f64], states: &mut [f64], input: &[f64], internal_tick: usize) {
+    states[..INPUT_SIZE].copy_from_slice(input);
+    let previous = states.to_vec();
+    let neuron_data_length = 2 + INTERNAL_SIZE - 1;
+
+    for (neuron, state) in states.iter_mut().enumerate().skip(INPUT_SIZE) {
+        let genes = &genome[neuron * neuron_data_length..(neuron + 1) * neuron_data_length];
+        if !internal_tick.is_multiple_of(genes[1].round().max(1.0) as usize) {
+            continue;
+        }
+
+        let sources = (0..INTERNAL_SIZE).filter(|source| *source != neuron);
+        let sum: f64 = genes[2..]
+            .iter()
+            .zip(sources)
+            .map(|(weight, source)| weight * previous[source])
+            .sum();
+        *state = (sum + genes[0]).tanh();
+    }
+}
+
+#[test]
+fn matrix_tick_matches_per_neuron_tick() {
+    let mut layer =
+        ThinkingLayer::new(INPUT_SIZE, INTERNAL_SIZE, 2, ActivationFunction::Tanh).unwrap();
+    let neuron_data_length = 2 + INTERNAL_SIZE - 1;
+    let genome: Vec<f64> = (0..layer.genome_length())
+        .map(|index| match index % neuron_data_length {
+            // Delays of one to three ticks.
+            1 => (1 + index % 3) as f64,
+            _ => (index as f64 * 0.7).sin() * 0.5,
+        })
+        .collect();
+    layer.set_genome(genome.clone()).unwrap();
+
+    let mut states = vec![0.0; INTERNAL_SIZE];
+    for tick in 1..=12 {
+        let input: Vec<f64> = (0..INPUT_SIZE)
+            .map(|index| ((tick + index) as f64).cos())
+            .collect();
+        per_neuron_tick(&genome, &mut states, &input, tick);
+        layer.tick(Some(&input));
+
+        for (actual, expected) in layer.neuron_states().iter().zip(&states) {
+            assert!((actual - expected).abs() < 1e-12, "Tick {} differs", tick);
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use core_crnn::activation_function::ActivationFunction;
+use core_crnn::network::{self, Converted, Network};
+use core_crnn::thinking_layer::ThinkingLayer;
+
+fn layer() -> ThinkingLayer {
+    ThinkingLayer::new(3, 16, 2, ActivationFunction::Tanh).unwrap()
+}
+
+fn input(tick: usize) -> Vec<f64> {
+    (0..3).map(|index| ((tick + index) as f64).sin()).collect()
+}
+
+#[test]
+fn f32_layers_tick_like_f64_ones() {
+    let mut layer = layer();
+    let mut single = layer.convert::<f32>();
+
+    for tick in 0..20 {
+        let input = input(tick);
+        let single_input: Vec<f32> = input.iter().map(|value| *value as f32).collect();
+        layer.tick(Some(&input));
+        single.tick(Some(&single_input));
+
+        for (single, double) in single.output().iter().zip(layer.output()) {
+            assert!((*single as f64 - double).abs() < 1e-4, "Tick {}", tick);
+        }
+    }
+}
+
+#[test]
+fn conversion_round_trips() {
+    let layer = layer();
+    let single = layer.convert::<f32>();
+    let rounded: Vec<f64> = layer
+        .genome()
+        .iter()
+        .map(|gene| *gene as f32 as f64)
+        .collect();
+
+    assert_eq!(single.convert::<f64>().genome(), rounded);
+    assert_eq!(
+        single.convert::<f64>().convert::<f32>().genome(),
+        single.genome()
+    );
+    assert_eq!(
+        single.snapshot(),
+        single.convert::<f64>().convert().snapshot()
+    );
+}
+
+#[test]
+fn converted_networks_take_other_float_types() {
+    let mut layer = layer();
+    let mut converted: Box<dyn Network<f32> + Send> = network::boxed(layer.clone());
+    let mut same: Box<dyn Network<f64> + Send> = network::boxed(layer.clone());
+    let mut wrapped = Converted::new(layer.clone());
+
+    for tick in 0..5 {
+        let single_input: Vec<f32> = input(tick).iter().map(|value| *value as f32).collect();
+        // What the converted networks see after their inputs were rounded.
+        let input: Vec<f64> = single_input.iter().map(|value| *value as f64).collect();
+        layer.tick(Some(&input));
+        converted.tick_for(Some(&single_input), 1.0);
+        same.tick_for(Some(&input), 1.0);
+        Network::<f32>::tick_for(&mut wrapped, Some(&single_input), 1.0);
+    }
+
+    let rounded: Vec<f32> = layer.output().iter().map(|value| *value as f32).collect();
+    assert_eq!(converted.output(), rounded);
+    assert_eq!(Network::<f32>::output(&wrapped), rounded);
+    assert_eq!(same.output(), layer.output());
+}
@@ -0,0 +1,142 @@
+use core_crnn::activation_function::ActivationFunction;
+use core_crnn::crossover::Crossover;
+use core_crnn::genome::{GeneKind, Genome, GenomeError};
+use core_crnn::mutation::{Mutation, MutationOperator};
+use core_crnn::persisted_model::{BinaryPrecision, ModelMetadata, PersistedModel};
+use core_crnn::thinking_layer::{ThinkingLayer, MAX_DELAY, MIN_DELAY};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+fn layer() -> ThinkingLayer {
+    ThinkingLayer::new(2, 6, 2, ActivationFunction::Tanh).unwrap()
+}
+
+/// Error of loading `layer`'s genome after `change`, which must leave the layer untouched.
+fn rejection(change: impl FnOnce(&mut Vec<f64>)) -> GenomeError {
+    let mut layer = layer();
+    let (genome, snapshot) = (layer.genome(), layer.snapshot());
+    let mut broken = genome.clone();
+    change(&mut broken);
+
+    let error = layer.set_genome(broken).unwrap_err();
+    assert_eq!(layer.genome(), genome);
+    assert_eq!(layer.snapshot(), snapshot);
+    error
+}
+
+#[test]
+fn genomes_of_the_wrong_length_are_rejected() {
+    let expected = layer().genome_length();
+    assert_eq!(
+        rejection(|genome| genome.push(0.0)),
+        GenomeError::LengthMismatch {
+            expected,
+            actual: expected + 1,
+        }
+    );
+    assert_eq!(
+        rejection(Vec::clear),
+        GenomeError::LengthMismatch {
+            expected,
+            actual: 0,
+        }
+    );
+}
+
+#[test]
+fn non_finite_genes_are_rejected() {
+    for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+        let error = rejection(|genome| genome[4] = value);
+        let GenomeError::NonFiniteGene { index, .. } = error else {
+            panic!("{value} was not reported as non-finite: {error:?}")
+        };
+        assert_eq!(index, 4);
+    }
+}
+
+#[test]
+fn delays_out_of_range_are_rejected() {
+    // The delay of the second neuron follows its bias.
+    let delay_index = 1 + layer().genome_length() / layer().internal_size();
+    for delay in [MIN_DELAY - 0.5, MAX_DELAY * 2.0] {
+        assert_eq!(
+            rejection(|genome| genome[delay_index] = delay),
+            GenomeError::DelayOutOfRange { neuron: 1, delay }
+        );
+    }
+
+    let mut layer = layer();
+    let mut genome = layer.genome();
+    genome[delay_index] = MIN_DELAY;
+    layer.set_genome(genome).unwrap();
+}
+
+#[test]
+fn mutation_and_crossover_keep_delays_in_range() {
+    let mut rng = StdRng::seed_from_u64(1);
+    let push_down = Mutation::new()
+        .rule(
+            GeneKind::Delay,
+            1.0,
+            MutationOperator::UniformReset {
+                min: -10.0,
+                max: -5.0,
+            },
+        )
+        .unwrap();
+
+    let mut parent_a = layer();
+    parent_a.mutate(&push_down, &mut rng);
+    assert!(parent_a.delays().iter().all(|delay| *delay == MIN_DELAY));
+
+    // Spreading children far beyond their parents pushes some delays below zero.
+    let parent_b = layer();
+    let crossover = Crossover::simulated_binary(0.0).unwrap();
+    for child in ThinkingLayer::crossover(&parent_a, &parent_b, &crossover, 20, &mut rng).unwrap() {
+        assert!(child
+            .delays()
+            .iter()
+            .all(|delay| (MIN_DELAY..=MAX_DELAY).contains(delay)));
+    }
+}
+
+#[test]
+fn clamped_f32_delays_stay_loadable() {
+    let mut rng = StdRng::seed_from_u64(2);
+    let push_up = Mutation::new()
+        .rule(
+            GeneKind::Delay,
+            1.0,
+            MutationOperator::UniformReset {
+                min: 1e12,
+                max: 2e12,
+            },
+        )
+        .unwrap();
+
+    let mut layer = layer().convert::<f32>();
+    layer.mutate(&push_up, &mut rng);
+    assert!(layer
+        .delays()
+        .iter()
+        .all(|delay| *delay as f64 == MAX_DELAY));
+
+    layer.set_genome(layer.genome()).unwrap();
+    ThinkingLayer::crossover(&layer, &layer.clone(), &Crossover::Blend, 1, &mut rng).unwrap();
+    let json = PersistedModel::from_layer(&layer, ModelMetadata::default())
+        .unwrap()
+        .to_json()
+        .unwrap();
+    PersistedModel::from_json(json.as_bytes()).unwrap();
+
+    let mut layer = layer.convert::<f64>();
+    layer.mutate(&push_up, &mut rng);
+    let bytes = PersistedModel::from_layer(&layer, ModelMetadata::default())
+        .unwrap()
+        .to_binary(BinaryPrecision::F32)
+        .unwrap();
+    PersistedModel::from_binary(&bytes)
+        .unwrap()
+        .into_layer::<f32>()
+        .unwrap();
+}
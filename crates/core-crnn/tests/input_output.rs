@@ -0,0 +1,161 @@
+use core_crnn::activation_function::ActivationFunction;
+use core_crnn::genome::GeneKind;
+use core_crnn::layer_settings::{InputEncoding, LayerSettings, OutputReadout};
+use core_crnn::persisted_model::{ModelMetadata, PersistedModel};
+use core_crnn::thinking_layer::ThinkingLayer;
+use core_crnn::thinking_layer_batch::ThinkingLayerBatch;
+
+fn layer(settings: LayerSettings) -> ThinkingLayer {
+    ThinkingLayer::with_settings(2, 8, 3, ActivationFunction::Tanh, settings).unwrap()
+}
+
+#[test]
+fn scaled_inputs_are_mapped_before_clamping() {
+    let mut layer = layer(
+        LayerSettings::default().input_encoding(InputEncoding::Scaled {
+            scale: 2.0,
+            offset: 0.5,
+        }),
+    );
+
+    layer.tick(Some(&[1.0, -0.25]));
+    assert_eq!(&layer.neuron_states()[..2], [2.5, 0.0]);
+}
+
+#[test]
+fn additive_inputs_drive_input_neurons_as_currents() {
+    let mut layer = layer(LayerSettings::default().input_encoding(InputEncoding::Additive));
+    let genome = layer
+        .classified_genes()
+        .map(|(kind, gene)| if kind == GeneKind::Delay { 1.0 } else { *gene })
+        .collect();
+    layer.set_genome(genome).unwrap();
+
+    let mut without_input = layer.clone();
+    layer.tick(Some(&[1.0, -1.0]));
+    without_input.tick(None);
+
+    // Input neurons are updated by the network, the input only shifts their weighted sums.
+    assert_ne!(layer.neuron_states()[..2], [1.0, -1.0]);
+    assert_ne!(
+        layer.neuron_states()[..2],
+        without_input.neuron_states()[..2]
+    );
+
+    // The current stays until it is replaced or the state is reset.
+    let state = layer.snapshot();
+    assert_eq!(state.input_current(), [1.0, -1.0]);
+    layer.reset_state();
+    assert_eq!(layer.snapshot().input_current(), [0.0, 0.0]);
+}
+
+#[test]
+fn linear_readout_is_part_of_the_genome() {
+    let raw = layer(LayerSettings::default());
+    let mut linear = layer(LayerSettings::default().output_readout(OutputReadout::Linear));
+
+    let readout_genes = linear
+        .classified_genes()
+        .filter(|(kind, _)| *kind == GeneKind::Readout)
+        .count();
+    assert_eq!(readout_genes, 3 * (8 + 1));
+    assert_eq!(linear.genome().len(), raw.genome().len() + readout_genes);
+
+    // Only biases of one, two and three, so the outputs are those regardless of the states.
+    let mut readout_index = 0;
+    let genome = linear
+        .classified_genes()
+        .map(|(kind, _)| {
+            if kind != GeneKind::Readout {
+                return 0.0;
+            }
+            readout_index += 1;
+            match (readout_index - 1) % 9 {
+                0 => ((readout_index - 1) / 9 + 1) as f64,
+                _ => 0.0,
+            }
+        })
+        .collect();
+    linear.set_genome(genome).unwrap();
+    linear.tick(Some(&[1.0, -1.0]));
+    assert_eq!(linear.output(), [1.0, 2.0, 3.0]);
+
+    let restored: ThinkingLayer = PersistedModel::from_layer(&linear, ModelMetadata::default())
+        .unwrap()
+        .into_layer()
+        .unwrap();
+    assert_eq!(restored.readout_weights(), linear.readout_weights());
+
+    assert!(ThinkingLayerBatch::from_layers(&[linear]).is_err());
+}
+
+#[test]
+fn softmax_outputs_are_a_distribution() {
+    let mut layer = layer(LayerSettings::default().output_readout(OutputReadout::Softmax));
+    for _ in 0..5 {
+        layer.tick(Some(&[1.0, -0.5]));
+    }
+
+    let output = layer.output();
+    assert_eq!(output.len(), 3);
+    assert!(output.iter().all(|value| *value > 0.0));
+    assert!((output.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+}
+
+#[test]
+fn inputs_of_the_wrong_length_are_rejected() {
+    let mut layer = layer(LayerSettings::default());
+
+    assert!(layer.set_input(&[1.0]).is_err());
+    assert!(layer.set_input(&[1.0, 2.0, 3.0]).is_err());
+    assert!(layer.set_input_channel(2, 1.0).is_err());
+    assert_eq!(layer.neuron_states(), [0.0; 8]);
+
+    layer.set_input(&[1.0, 2.0]).unwrap();
+    assert_eq!(&layer.neuron_states()[..2], [1.0, 2.0]);
+}
+
+#[test]
+#[should_panic(expected = "expects 2 input values, got 3")]
+fn ticking_with_the_wrong_input_length_panics() {
+    layer(LayerSettings::default()).tick(Some(&[1.0, 2.0, 3.0]));
+}
+
+#[test]
+fn named_inputs_update_only_their_channels() {
+    let mut layer = layer(
+        LayerSettings::default()
+            .input_names(["paddle", "ball"])
+            .input_encoding(InputEncoding::Scaled {
+                scale: 2.0,
+                offset: 0.0,
+            }),
+    );
+    layer.set_input(&[1.0, 1.0]).unwrap();
+
+    layer.set_named_inputs(&[("ball", 0.25)]).unwrap();
+    assert_eq!(&layer.neuron_states()[..2], [2.0, 0.5]);
+    assert!(layer.set_named_inputs(&[("score", 1.0)]).is_err());
+    // An unknown name anywhere leaves every channel alone.
+    assert!(layer
+        .set_named_inputs(&[("paddle", 5.0), ("score", 1.0)])
+        .is_err());
+    assert_eq!(&layer.neuron_states()[..2], [2.0, 0.5]);
+
+    let restored: ThinkingLayer = PersistedModel::from_layer(&layer, ModelMetadata::default())
+        .unwrap()
+        .into_layer()
+        .unwrap();
+    assert_eq!(restored.input_channel("ball"), Some(1));
+
+    for input_names in [vec!["paddle"], vec!["ball", "ball"]] {
+        assert!(ThinkingLayer::<f64>::with_settings(
+            2,
+            8,
+            3,
+            ActivationFunction::Tanh,
+            LayerSettings::default().input_names(input_names),
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,44 @@
+use core_crnn::kernel::dot;
+#[cfg(feature = "simd")]
+use core_crnn::kernel::{dot_scalar, simd};
+#[cfg(feature = "simd")]
+use rand::{rng, Rng};
+
+#[cfg(feature = "simd")]
+#[test]
+fn simd_dot_matches_scalar_f32() {
+    let mut rng = rng();
+    for len in 0..100 {
+        let a: Vec<f32> = (0..len).map(|_| rng.random_range(-1.0..1.0)).collect();
+        let b: Vec<f32> = (0..len).map(|_| rng.random_range(-1.0..1.0)).collect();
+
+        assert_eq!(
+            simd::dot_f32(&a, &b).to_bits(),
+            dot_scalar(&a, &b).to_bits()
+        );
+    }
+}
+
+#[cfg(feature = "simd")]
+#[test]
+fn simd_dot_matches_scalar_f64() {
+    let mut rng = rng();
+    for len in 0..100 {
+        let a: Vec<f64> = (0..len).map(|_| rng.random_range(-1.0..1.0)).collect();
+        let b: Vec<f64> = (0..len).map(|_| rng.random_range(-1.0..1.0)).collect();
+
+        assert_eq!(
+            simd::dot_f64(&a, &b).to_bits(),
+            dot_scalar(&a, &b).to_bits()
+        );
+    }
+}
+
+#[test]
+fn dot_matches_naive_sum_within_tolerance() {
+    let a: Vec<f64> = (0..1000).map(|i| (i as f64).sin()).collect();
+    let b: Vec<f64> = (0..1000).map(|i| (i as f64).cos()).collect();
+    let naive: f64 = a.iter().zip(&b).map(|(a, b)| a * b).sum();
+
+    assert!((dot(&a, &b) - naive).abs() < 1e-9);
+}
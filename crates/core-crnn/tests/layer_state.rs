@@ -0,0 +1,46 @@
+use core_crnn::activation_function::ActivationFunction;
+use core_crnn::layer_settings::{LayerSettings, Plasticity};
+use core_crnn::thinking_layer::ThinkingLayer;
+
+fn plastic_layer() -> ThinkingLayer {
+    ThinkingLayer::with_settings(
+        2,
+        12,
+        2,
+        ActivationFunction::Tanh,
+        LayerSettings::default().plasticity(Plasticity::Hebbian),
+    )
+    .unwrap()
+}
+
+fn run(layer: &mut ThinkingLayer, ticks: usize) -> Vec<f64> {
+    for tick in 0..ticks {
+        layer.tick(Some(&[tick as f64 / 10.0, 1.0]));
+    }
+    layer.output()
+}
+
+#[test]
+fn reset_state_makes_episodes_reproducible() {
+    let mut layer = plastic_layer();
+    let fresh = layer.snapshot();
+
+    let first = run(&mut layer, 15);
+    layer.reset_state();
+    assert_eq!(layer.snapshot(), fresh);
+    assert_eq!(run(&mut layer, 15), first);
+}
+
+#[test]
+fn restored_snapshots_branch_identically() {
+    let mut layer = plastic_layer();
+    run(&mut layer, 7);
+    let branch_point = layer.snapshot();
+
+    let first = run(&mut layer, 9);
+    layer.restore(&branch_point).unwrap();
+    assert_eq!(run(&mut layer, 9), first);
+
+    let mut other: ThinkingLayer = ThinkingLayer::new(2, 8, 2, ActivationFunction::Tanh).unwrap();
+    assert!(other.restore(&branch_point).is_err());
+}
@@ -0,0 +1,101 @@
+use core_crnn::activation_function::ActivationFunction;
+use core_crnn::genome::{GeneKind, Genome};
+use core_crnn::mutation::{Mutation, MutationOperator};
+use core_crnn::thinking_layer::{ThinkingLayer, MIN_DELAY};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+fn layer() -> ThinkingLayer {
+    ThinkingLayer::new(2, 10, 2, ActivationFunction::Tanh).unwrap()
+}
+
+/// Pairs of genes before and after applying `mutation`, with their kind.
+fn mutated(mutation: Mutation) -> Vec<(GeneKind, f64, f64)> {
+    let mut layer = layer();
+    let before: Vec<_> = layer
+        .classified_genes()
+        .map(|(kind, gene)| (kind, *gene))
+        .collect();
+    layer.mutate(&mutation, &mut StdRng::seed_from_u64(1));
+
+    before
+        .into_iter()
+        .zip(layer.genome())
+        .map(|((kind, before), after)| (kind, before, after))
+        .collect()
+}
+
+#[test]
+fn rules_only_touch_their_gene_class() {
+    let genes = mutated(
+        Mutation::new()
+            .rule(GeneKind::Bias, 1.0, MutationOperator::Cauchy { scale: 0.1 })
+            .unwrap(),
+    );
+
+    for (kind, before, after) in genes {
+        assert_eq!(kind == GeneKind::Bias, before != after, "{:?}", kind);
+    }
+}
+
+#[test]
+fn operators_change_genes_as_documented() {
+    for (kind, _, after) in mutated(
+        Mutation::new()
+            .rule(GeneKind::Weight, 1.0, MutationOperator::Prune)
+            .unwrap(),
+    ) {
+        if kind == GeneKind::Weight {
+            assert_eq!(after, 0.0);
+        }
+    }
+
+    let reset = MutationOperator::UniformReset { min: 2.0, max: 3.0 };
+    for (_, _, after) in mutated(Mutation::new().rule(None, 1.0, reset).unwrap()) {
+        assert!((2.0..3.0).contains(&after));
+    }
+
+    let step = MutationOperator::IntegerStep { max_step: 2 };
+    for (kind, before, after) in mutated(Mutation::new().rule(GeneKind::Delay, 1.0, step).unwrap())
+    {
+        // Delays stepped below the minimum end up on it.
+        if kind == GeneKind::Delay && after != MIN_DELAY {
+            let change = (after - before).abs();
+            assert!([1.0, 2.0].iter().any(|step| (change - step).abs() < 1e-9));
+        }
+    }
+}
+
+#[test]
+fn rules_without_probability_do_nothing() {
+    let genes = mutated(
+        Mutation::gaussian(0.0, 1.0)
+            .unwrap()
+            .rule(None, 0.0, MutationOperator::Prune)
+            .unwrap(),
+    );
+    assert!(genes.iter().all(|(_, before, after)| before == after));
+}
+
+#[test]
+fn rules_with_invalid_parameters_are_rejected() {
+    for operator in [
+        MutationOperator::Gaussian { strength: -1.0 },
+        MutationOperator::Gaussian { strength: f64::NAN },
+        MutationOperator::Cauchy { scale: 0.0 },
+        MutationOperator::UniformReset { min: 1.0, max: 1.0 },
+        MutationOperator::UniformReset {
+            min: 0.0,
+            max: f64::INFINITY,
+        },
+        MutationOperator::IntegerStep { max_step: 0 },
+    ] {
+        assert!(
+            Mutation::new().rule(None, 0.5, operator).is_err(),
+            "{operator:?}"
+        );
+    }
+
+    assert!(Mutation::gaussian(1.5, 0.1).is_err());
+    assert!(Mutation::gaussian(1.0, 0.0).is_ok());
+}
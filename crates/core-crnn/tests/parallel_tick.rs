@@ -0,0 +1,34 @@
+use core_crnn::activation_function::ActivationFunction;
+use core_crnn::kernel::dot_scalar;
+use core_crnn::thinking_layer::{ThinkingLayer, PARALLEL_TICK_THRESHOLD};
+
+/// One tick of a gated layer computed serially neuron by neuron, the same way with or without
+/// the `parallel` feature.
+fn serial_tick(layer: &ThinkingLayer, states: &mut [f64], input: &[f64], tick: usize) {
+    states[..input.len()].copy_from_slice(input);
+    let previous = states.to_vec();
+
+    for (neuron, state) in states.iter_mut().enumerate().skip(layer.input_size()) {
+        let delay = layer.delays()[neuron].round().max(1.0) as usize;
+        if tick.is_multiple_of(delay) {
+            let sum = dot_scalar(layer.input_weights(neuron), &previous);
+            *state = (sum + layer.biases()[neuron]).tanh();
+        }
+    }
+}
+
+#[test]
+fn large_layers_tick_like_serial_ones() {
+    let internal_size = PARALLEL_TICK_THRESHOLD + 44;
+    let mut layer = ThinkingLayer::new(4, internal_size, 3, ActivationFunction::Tanh).unwrap();
+    let mut states = layer.neuron_states().to_vec();
+
+    for tick in 1..=20 {
+        let input: Vec<f64> = (0..4).map(|index| ((tick * index) as f64).sin()).collect();
+        serial_tick(&layer, &mut states, &input, tick);
+        layer.tick(Some(&input));
+
+        assert_eq!(layer.neuron_states(), states, "Tick {} differs", tick);
+    }
+    assert_eq!(layer.output(), states[internal_size - 3..]);
+}
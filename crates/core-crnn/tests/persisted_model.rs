@@ -0,0 +1,153 @@
+use core_crnn::activation_function::ActivationFunction;
+use core_crnn::persisted_model::{
+    BinaryPrecision, ModelMetadata, PersistedModel, MODEL_FORMAT_VERSION,
+};
+use core_crnn::thinking_layer::ThinkingLayer;
+
+fn trained_model() -> PersistedModel {
+    let mut layer = ThinkingLayer::new(3, 16, 2, ActivationFunction::Tanh).unwrap();
+    for _ in 0..10 {
+        layer.tick(Some(&[0.3, -0.2, 0.9]));
+    }
+
+    PersistedModel::from_layer(&layer, ModelMetadata::default().score(4.5).generation(7)).unwrap()
+}
+
+#[test]
+fn binary_f64_round_trip_matches_json() {
+    let model = trained_model();
+
+    let from_json = PersistedModel::from_json(model.to_json().unwrap().as_bytes()).unwrap();
+    let from_binary =
+        PersistedModel::from_binary(&model.to_binary(BinaryPrecision::F64).unwrap()).unwrap();
+
+    assert_eq!(from_binary.genome, from_json.genome);
+    assert_eq!(from_binary.neuron_states, from_json.neuron_states);
+    assert_eq!(from_binary.internal_tick, from_json.internal_tick);
+    assert_eq!(from_binary.internal_size, from_json.internal_size);
+    assert_eq!(from_binary.metadata.score, Some(4.5));
+    assert_eq!(from_binary.metadata.generation, Some(7));
+}
+
+#[test]
+fn binary_f32_round_trip_is_close_and_smaller() {
+    let model = trained_model();
+
+    let f32_bytes = model.to_binary(BinaryPrecision::F32).unwrap();
+    let f64_bytes = model.to_binary(BinaryPrecision::F64).unwrap();
+    assert!(f32_bytes.len() < f64_bytes.len());
+
+    let restored = PersistedModel::from_binary(&f32_bytes).unwrap();
+    for (original, restored) in model.genome.iter().zip(&restored.genome) {
+        assert_eq!(*original as f32 as f64, *restored);
+    }
+}
+
+#[test]
+fn binary_rejects_corruption() {
+    let mut bytes = trained_model().to_binary(BinaryPrecision::F64).unwrap();
+    let middle = bytes.len() / 2;
+    bytes[middle] ^= 0xff;
+
+    assert!(PersistedModel::from_binary(&bytes).is_err());
+    assert!(PersistedModel::from_binary(&bytes[..bytes.len() / 3]).is_err());
+}
+
+#[test]
+fn restored_layer_behaves_identically() {
+    let model = trained_model();
+    let mut original = model.clone().into_layer().unwrap();
+    let mut restored = PersistedModel::from_binary(&model.to_binary(BinaryPrecision::F64).unwrap())
+        .unwrap()
+        .into_layer()
+        .unwrap();
+
+    for _ in 0..5 {
+        original.tick(Some(&[0.1, 0.2, 0.3]));
+        restored.tick(Some(&[0.1, 0.2, 0.3]));
+    }
+
+    assert_eq!(original.output(), restored.output());
+}
+
+#[test]
+fn json_rejects_mismatched_genome() {
+    let mut model = trained_model();
+    model.genome.pop();
+
+    assert!(PersistedModel::from_json(model.to_json().unwrap().as_bytes()).is_err());
+}
+
+#[test]
+fn json_rejects_oversized_model() {
+    let mut model = trained_model();
+    model.input_size = usize::MAX;
+    assert!(PersistedModel::from_json(model.to_json().unwrap().as_bytes()).is_err());
+
+    // Too many neurons to count the genes of.
+    let mut model = trained_model();
+    model.internal_size = usize::MAX / 2;
+    assert!(PersistedModel::from_json(model.to_json().unwrap().as_bytes()).is_err());
+}
+
+#[test]
+fn validation_rejects_inconsistent_models() {
+    let from_json =
+        |model: PersistedModel| PersistedModel::from_json(model.to_json().unwrap().as_bytes());
+
+    let mut model = trained_model();
+    model.format_version = MODEL_FORMAT_VERSION + 1;
+    assert!(from_json(model).is_err());
+
+    let mut model = trained_model();
+    model.genome_layout_version += 1;
+    assert!(from_json(model).is_err());
+
+    let mut model = trained_model();
+    model.output_size = 14;
+    assert!(from_json(model).is_err());
+
+    let mut model = trained_model();
+    model.neuron_states.pop();
+    assert!(from_json(model).is_err());
+
+    let mut model = trained_model();
+    model.sources = vec![1; 16];
+    assert!(from_json(model).is_err());
+
+    // Files of older versions, which lack the later fields, still load.
+    let mut json: serde_json::Value =
+        serde_json::from_str(&trained_model().to_json().unwrap()).unwrap();
+    json["format_version"] = 1.into();
+    let object = json.as_object_mut().unwrap();
+    object.remove("settings");
+    object.remove("sources");
+    assert!(PersistedModel::from_json(json.to_string().as_bytes()).is_ok());
+}
+
+#[test]
+fn legacy_genome_files_load_with_given_sizes() {
+    let layer: ThinkingLayer = ThinkingLayer::new(3, 16, 2, ActivationFunction::Tanh).unwrap();
+    let json = format!(r#"{{"score":2.5,"genome":{:?}}}"#, layer.genome());
+
+    let model =
+        PersistedModel::from_legacy_json(json.as_bytes(), 3, 16, 2, ActivationFunction::Tanh)
+            .unwrap();
+    assert_eq!(model.metadata.score, Some(2.5));
+    assert_eq!(model.into_layer::<f64>().unwrap().genome(), layer.genome());
+
+    // Sizes that do not fit the genome are caught.
+    assert!(
+        PersistedModel::from_legacy_json(json.as_bytes(), 3, 17, 2, ActivationFunction::Tanh)
+            .is_err()
+    );
+
+    // Delays older trainers mutated below zero are clamped.
+    let mut genome = layer.genome();
+    genome[1] = -2.0;
+    let json = format!(r#"{{"score":2.5,"genome":{:?}}}"#, genome);
+    let model =
+        PersistedModel::from_legacy_json(json.as_bytes(), 3, 16, 2, ActivationFunction::Tanh)
+            .unwrap();
+    assert_eq!(model.genome[1], 0.0);
+}
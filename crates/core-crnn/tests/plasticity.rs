@@ -0,0 +1,106 @@
+use core_crnn::activation_function::ActivationFunction;
+use core_crnn::genome::{GeneKind, Genome};
+use core_crnn::layer_settings::{Connectivity, LayerSettings, Plasticity};
+use core_crnn::mutation::Mutation;
+use core_crnn::thinking_layer::{ThinkingLayer, HEBBIAN_COEFFICIENTS};
+use rand::rng;
+
+fn plastic_layer(connectivity: Connectivity) -> ThinkingLayer {
+    ThinkingLayer::with_settings(
+        2,
+        10,
+        2,
+        ActivationFunction::Tanh,
+        LayerSettings::default()
+            .connectivity(connectivity)
+            .plasticity(Plasticity::Hebbian),
+    )
+    .unwrap()
+}
+
+#[test]
+fn every_connection_carries_hebbian_coefficients() {
+    for (connectivity, fan_in) in [
+        (Connectivity::Dense, 9),
+        (Connectivity::Random { fan_in: 4 }, 4),
+    ] {
+        let mut layer = plastic_layer(connectivity);
+        assert_eq!(
+            layer.genome().len(),
+            10 * (2 + fan_in * (1 + HEBBIAN_COEFFICIENTS))
+        );
+        assert_eq!(
+            layer
+                .classified_genes()
+                .filter(|(kind, _)| *kind == GeneKind::Plasticity)
+                .count(),
+            10 * fan_in * HEBBIAN_COEFFICIENTS
+        );
+
+        let genome = layer.genome();
+        layer.set_genome(genome.clone()).unwrap();
+        assert_eq!(layer.genome(), genome);
+    }
+}
+
+#[test]
+fn weights_adapt_during_an_episode_and_reset_to_the_genome() {
+    let mut layer = plastic_layer(Connectivity::Dense);
+    let genome = layer.genome();
+
+    for _ in 0..20 {
+        layer.tick(Some(&[1.0, -1.0]));
+    }
+
+    assert_ne!(layer.effective_weights(), layer.weights());
+    assert_eq!(layer.genome(), genome);
+    for neuron_index in 0..10 {
+        assert_eq!(
+            layer.effective_weights()[neuron_index * 10 + neuron_index],
+            0.0
+        );
+    }
+
+    layer.reset_plastic_weights();
+    assert_eq!(layer.effective_weights(), layer.weights());
+}
+
+#[test]
+fn zero_coefficients_behave_like_static_weights() {
+    let mut plastic = plastic_layer(Connectivity::Local { fan_in: 4 });
+    let genome: Vec<f64> = plastic
+        .classified_genes()
+        .map(|(kind, gene)| match kind {
+            GeneKind::Plasticity => 0.0,
+            _ => *gene,
+        })
+        .collect();
+    plastic.set_genome(genome).unwrap();
+
+    let mut fixed: ThinkingLayer = ThinkingLayer::with_settings(
+        2,
+        10,
+        2,
+        ActivationFunction::Tanh,
+        LayerSettings::default().connectivity(Connectivity::Local { fan_in: 4 }),
+    )
+    .unwrap();
+    fixed
+        .set_genome(
+            plastic
+                .classified_genes()
+                .filter(|(kind, _)| *kind != GeneKind::Plasticity)
+                .map(|(_, gene)| *gene)
+                .collect(),
+        )
+        .unwrap();
+
+    plastic.mutate(&Mutation::gaussian(0.0, 0.1).unwrap(), &mut rng());
+    for _ in 0..20 {
+        plastic.tick(Some(&[1.0, -1.0]));
+        fixed.tick(Some(&[1.0, -1.0]));
+    }
+
+    assert_eq!(plastic.effective_weights(), plastic.weights());
+    assert_eq!(plastic.output(), fixed.output());
+}
@@ -0,0 +1,37 @@
+use core_crnn::activation_function::ActivationFunction;
+use core_crnn::crossover::Crossover;
+use core_crnn::genome::Genome;
+use core_crnn::layer_settings::{Connectivity, LayerSettings};
+use core_crnn::mutation::Mutation;
+use core_crnn::thinking_layer::ThinkingLayer;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+fn evolve(seed: u64) -> Vec<ThinkingLayer> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let settings = LayerSettings::default()
+        .connectivity(Connectivity::Random { fan_in: 3 })
+        .activation_palette(vec![ActivationFunction::Tanh, ActivationFunction::Sine]);
+    let parent_a: ThinkingLayer =
+        ThinkingLayer::with_rng(2, 10, 2, ActivationFunction::Tanh, settings, &mut rng).unwrap();
+    let mut parent_b = parent_a.clone();
+    parent_b.mutate(&Mutation::gaussian(0.5, 0.2).unwrap(), &mut rng);
+
+    let mut children =
+        ThinkingLayer::crossover(&parent_a, &parent_b, &Crossover::Blend, 2, &mut rng).unwrap();
+    children.push(parent_b);
+    children
+}
+
+#[test]
+fn same_seed_yields_same_layers() {
+    let genomes = |layers: Vec<ThinkingLayer>| -> Vec<(Vec<f64>, Vec<u32>)> {
+        layers
+            .into_iter()
+            .map(|layer| (layer.genome(), layer.sources().to_vec()))
+            .collect()
+    };
+
+    assert_eq!(genomes(evolve(7)), genomes(evolve(7)));
+    assert_ne!(genomes(evolve(7)), genomes(evolve(8)));
+}
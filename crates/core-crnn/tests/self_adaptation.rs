@@ -0,0 +1,84 @@
+use core_crnn::activation_function::ActivationFunction;
+use core_crnn::genome::{GeneKind, Genome, GenomeError};
+use core_crnn::layer_settings::{LayerSettings, SelfAdaptation};
+use core_crnn::mutation::Mutation;
+use core_crnn::thinking_layer::ThinkingLayer;
+use core_crnn::thinking_layer_batch::ThinkingLayerBatch;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+fn layer(self_adaptation: SelfAdaptation) -> ThinkingLayer {
+    ThinkingLayer::with_settings(
+        2,
+        8,
+        2,
+        ActivationFunction::Tanh,
+        LayerSettings::default().self_adaptation(self_adaptation),
+    )
+    .unwrap()
+}
+
+/// Replaces every step size gene of `layer` with `step_size`.
+fn set_step_sizes(layer: &mut ThinkingLayer, step_size: f64) -> Result<(), GenomeError> {
+    let genome = layer
+        .classified_genes()
+        .map(|(kind, gene)| {
+            if kind == GeneKind::StepSize {
+                step_size
+            } else {
+                *gene
+            }
+        })
+        .collect();
+    layer.set_genome(genome)
+}
+
+#[test]
+fn step_sizes_are_part_of_the_genome() {
+    let fixed = layer(SelfAdaptation::Fixed);
+    for (self_adaptation, count) in [
+        (SelfAdaptation::Global, 1),
+        (
+            SelfAdaptation::PerGeneClass,
+            SelfAdaptation::ADAPTED_KINDS.len(),
+        ),
+    ] {
+        let mut layer = layer(self_adaptation);
+        assert_eq!(layer.step_sizes(), vec![1.0; count]);
+        assert_eq!(layer.genome_length(), fixed.genome_length() + count);
+        assert_eq!(
+            layer.genome()[fixed.genome_length()..],
+            vec![1.0; count][..]
+        );
+
+        let batch = ThinkingLayerBatch::from_layers(&[layer.clone()]).unwrap();
+        assert_eq!(batch.layer(0).genome(), layer.genome());
+
+        assert!(matches!(
+            set_step_sizes(&mut layer, 0.0),
+            Err(GenomeError::StepSizeOutOfRange { .. })
+        ));
+    }
+}
+
+#[test]
+fn step_sizes_adapt_and_scale_mutations() {
+    let mut rng = StdRng::seed_from_u64(3);
+    let mut layer = layer(SelfAdaptation::PerGeneClass);
+
+    // Step sizes evolve even when no other gene is picked.
+    let genome = layer.genome();
+    layer.mutate(&Mutation::gaussian(0.0, 1.0).unwrap(), &mut rng);
+    assert!(layer.step_sizes().iter().all(|step_size| *step_size > 0.0));
+    assert_ne!(layer.step_sizes(), [1.0; 5]);
+    let length = genome.len() - 5;
+    assert_eq!(layer.genome()[..length], genome[..length]);
+
+    // Tiny step sizes keep even strong mutations close to the parent.
+    set_step_sizes(&mut layer, 1e-6).unwrap();
+    let genome = layer.genome();
+    layer.mutate(&Mutation::gaussian(1.0, 1.0).unwrap(), &mut rng);
+    for (before, after) in genome.iter().zip(layer.genome()).take(length) {
+        assert!((before - after).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,96 @@
+use core_crnn::activation_function::ActivationFunction;
+use core_crnn::crossover::Crossover;
+use core_crnn::genome::{GeneId, Genome};
+use core_crnn::layer_settings::{Connectivity, LayerSettings};
+use core_crnn::mutation::Mutation;
+use core_crnn::persisted_model::{BinaryPrecision, ModelMetadata, PersistedModel};
+use core_crnn::thinking_layer::ThinkingLayer;
+use rand::rng;
+use std::collections::HashMap;
+
+fn sparse_layer(connectivity: Connectivity) -> ThinkingLayer {
+    ThinkingLayer::with_settings(
+        3,
+        200,
+        2,
+        ActivationFunction::Tanh,
+        LayerSettings::default().connectivity(connectivity),
+    )
+    .unwrap()
+}
+
+#[test]
+fn sparse_genome_grows_linearly() {
+    for connectivity in [
+        Connectivity::Random { fan_in: 8 },
+        Connectivity::Local { fan_in: 8 },
+    ] {
+        let mut layer = sparse_layer(connectivity);
+        assert_eq!(layer.genome().len(), 200 * (2 + 8));
+
+        for neuron_index in 0..200 {
+            let sources = layer.input_sources(neuron_index).unwrap();
+            assert_eq!(sources.len(), 8);
+            assert!(!sources.contains(&(neuron_index as u32)));
+        }
+
+        layer.tick(Some(&[1.0, 0.5, -0.5]));
+        layer.mutate(&Mutation::gaussian(0.5, 0.1).unwrap(), &mut rng());
+        assert!(layer.output().iter().all(|value| value.is_finite()));
+    }
+}
+
+#[test]
+fn sparse_children_keep_parent_connections() {
+    let parent_a = sparse_layer(Connectivity::Random { fan_in: 5 });
+    let parent_b = sparse_layer(Connectivity::Random { fan_in: 5 });
+    assert_ne!(parent_a.sources(), parent_b.sources());
+    let genes = |layer: &ThinkingLayer| -> HashMap<GeneId, f64> {
+        layer.gene_ids().into_iter().zip(layer.genome()).collect()
+    };
+    let (genes_a, genes_b) = (genes(&parent_a), genes(&parent_b));
+
+    let children =
+        ThinkingLayer::crossover(&parent_a, &parent_b, &Crossover::Uniform, 2, &mut rng()).unwrap();
+
+    for (index, child) in children.iter().enumerate() {
+        let parent = [&parent_a, &parent_b][index % 2];
+        assert_eq!(child.sources(), parent.sources());
+        assert_eq!(child.genome().len(), parent.genome().len());
+
+        // Every connection weight comes from the same connection of either parent.
+        for (id, gene) in genes(child) {
+            assert!(
+                genes_a.get(&id) == Some(&gene) || genes_b.get(&id) == Some(&gene),
+                "{id:?} mixes unrelated connections"
+            );
+        }
+    }
+}
+
+#[test]
+fn sparse_layer_round_trips_through_model_file() {
+    let layer = sparse_layer(Connectivity::Random { fan_in: 4 });
+    let model = PersistedModel::from_layer(&layer, ModelMetadata::default()).unwrap();
+
+    let restored: ThinkingLayer =
+        PersistedModel::from_binary(&model.to_binary(BinaryPrecision::F64).unwrap())
+            .unwrap()
+            .into_layer()
+            .unwrap();
+
+    assert_eq!(restored.sources(), layer.sources());
+    assert_eq!(restored.genome(), layer.genome());
+}
+
+#[test]
+fn fan_in_larger_than_layer_is_rejected() {
+    assert!(ThinkingLayer::<f64>::with_settings(
+        1,
+        4,
+        1,
+        ActivationFunction::Tanh,
+        LayerSettings::default().connectivity(Connectivity::Local { fan_in: 4 }),
+    )
+    .is_err());
+}
@@ -0,0 +1,178 @@
+use core_crnn::activation_function::ActivationFunction;
+use core_crnn::crossover::Crossover;
+use core_crnn::genome::{Genome, GenomeError};
+use core_crnn::layer_settings::{LayerSettings, SelfAdaptation};
+use core_crnn::mutation::Mutation;
+use core_crnn::stacked_network::{Source, StackedNetwork};
+use core_crnn::thinking_layer::ThinkingLayer;
+use rand::rng;
+
+fn layers() -> (ThinkingLayer, ThinkingLayer) {
+    (
+        ThinkingLayer::new(2, 8, 3, ActivationFunction::Tanh).unwrap(),
+        ThinkingLayer::new(3, 6, 1, ActivationFunction::Sigmoid).unwrap(),
+    )
+}
+
+#[test]
+fn chained_layers_feed_each_other() {
+    let (mut first, mut second) = layers();
+    let mut network = StackedNetwork::chain(vec![first.clone(), second.clone()]).unwrap();
+    assert_eq!((network.input_size(), network.output_size()), (2, 1));
+
+    for tick in 0..5 {
+        let input = [tick as f64 / 5.0, 1.0];
+        first.tick(Some(&input));
+        second.tick(Some(&first.output()));
+        network.tick(Some(&input));
+    }
+
+    assert_eq!(network.output(), second.output());
+
+    network.reset_state();
+    assert!(network
+        .layers()
+        .iter()
+        .all(|layer| layer.neuron_states().iter().all(|state| *state == 0.0)));
+}
+
+#[test]
+fn wiring_is_validated() {
+    let (first, second) = layers();
+    let network = StackedNetwork::new(2);
+
+    // Wrong number of sources, unknown input and a layer reading from itself.
+    assert!(network
+        .clone()
+        .layer(first.clone(), vec![Source::Input(0)])
+        .is_err());
+    assert!(network
+        .clone()
+        .layer(first.clone(), vec![Source::Input(0), Source::Input(2)])
+        .is_err());
+    assert!(network
+        .clone()
+        .layer(
+            first.clone(),
+            vec![Source::Input(0), Source::Output { layer: 0, index: 0 }]
+        )
+        .is_err());
+
+    // Layers can mix network inputs and outputs of earlier layers.
+    let network = network
+        .layer(first, vec![Source::Input(1), Source::Input(0)])
+        .unwrap()
+        .layer(
+            second,
+            vec![
+                Source::Output { layer: 0, index: 2 },
+                Source::Input(0),
+                Source::Output { layer: 0, index: 0 },
+            ],
+        )
+        .unwrap();
+    assert!(network
+        .clone()
+        .outputs(vec![Source::Output { layer: 1, index: 1 }])
+        .is_err());
+    let network = network
+        .outputs(vec![
+            Source::Output { layer: 1, index: 0 },
+            Source::Input(1),
+        ])
+        .unwrap();
+    assert_eq!(network.output_size(), 2);
+}
+
+#[test]
+fn genome_spans_all_layers() {
+    let (first, second) = layers();
+    let mut network = StackedNetwork::chain(vec![first.clone(), second.clone()]).unwrap();
+
+    let genome = network.genome();
+    assert_eq!(genome.len(), first.genome_length() + second.genome_length());
+    assert_eq!(genome[..first.genome_length()], first.genome());
+
+    let mut broken = genome.clone();
+    broken[first.genome_length()] = f64::NAN;
+    // Indices refer to the whole genome and nothing is loaded.
+    let Err(GenomeError::NonFiniteGene { index, .. }) = network.load_genome(broken) else {
+        panic!("Non-finite gene was accepted")
+    };
+    assert_eq!(index, first.genome_length());
+    assert_eq!(network.genome(), genome);
+
+    let children =
+        StackedNetwork::crossover(&network, &network.clone(), &Crossover::Blend, 2, &mut rng())
+            .unwrap();
+    assert_eq!(children.len(), 4);
+    for mut child in children {
+        assert_eq!(child.genome().len(), genome.len());
+        child.mutate(&Mutation::gaussian(0.5, 0.1).unwrap(), &mut rng());
+        child.tick(Some(&[0.5, -0.5]));
+    }
+}
+
+#[test]
+fn load_errors_count_across_layers() {
+    let adapting = |input_size, internal_size, output_size| {
+        let settings = LayerSettings::default().self_adaptation(SelfAdaptation::PerGeneClass);
+        ThinkingLayer::with_settings(
+            input_size,
+            internal_size,
+            output_size,
+            ActivationFunction::Tanh,
+            settings,
+        )
+        .unwrap()
+    };
+    let (first, second): (ThinkingLayer, ThinkingLayer) = (adapting(2, 8, 3), adapting(3, 6, 1));
+    let mut network = StackedNetwork::chain(vec![first.clone(), second.clone()]).unwrap();
+    let genome = network.genome();
+    let first_step_sizes = first.step_sizes().len();
+
+    // The delay of the first neuron of the second layer follows its bias.
+    let mut broken = genome.clone();
+    broken[first.genome_length() + 1] = 1e12;
+    let Err(GenomeError::DelayOutOfRange { neuron, .. }) = network.load_genome(broken) else {
+        panic!("Delay out of range was accepted")
+    };
+    assert_eq!(neuron, first.internal_size());
+
+    let mut broken = genome.clone();
+    *broken.last_mut().unwrap() = -1.0;
+    let Err(GenomeError::StepSizeOutOfRange { index, .. }) = network.load_genome(broken) else {
+        panic!("Negative step size was accepted")
+    };
+    assert_eq!(index, first_step_sizes + second.step_sizes().len() - 1);
+    assert_eq!(network.genome(), genome);
+}
+
+#[test]
+fn differently_wired_networks_are_not_crossed() {
+    let (first, second) = layers();
+    let chained = StackedNetwork::chain(vec![first.clone(), second.clone()]).unwrap();
+    let crossed = StackedNetwork::new(2)
+        .layer(first, vec![Source::Input(1), Source::Input(0)])
+        .unwrap()
+        .layer(
+            second,
+            (0..3)
+                .map(|index| Source::Output { layer: 0, index })
+                .collect(),
+        )
+        .unwrap();
+    let rewired = crossed
+        .clone()
+        .outputs(vec![Source::Output { layer: 1, index: 0 }])
+        .unwrap();
+    let read_elsewhere = chained
+        .clone()
+        .outputs(vec![Source::Output { layer: 0, index: 0 }])
+        .unwrap();
+
+    for other in [&rewired, &read_elsewhere] {
+        let result = StackedNetwork::crossover(&chained, other, &Crossover::Blend, 1, &mut rng());
+        assert_eq!(result.unwrap_err(), GenomeError::StructureMismatch);
+    }
+}
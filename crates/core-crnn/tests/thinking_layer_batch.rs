@@ -0,0 +1,107 @@
+use core_crnn::activation_function::ActivationFunction;
+use core_crnn::genome::Genome;
+use core_crnn::layer_settings::{
+    Connectivity, InputEncoding, Integrator, LayerSettings, UpdateMode,
+};
+use core_crnn::mutation::Mutation;
+use core_crnn::thinking_layer::ThinkingLayer;
+use core_crnn::thinking_layer_batch::ThinkingLayerBatch;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+#[test]
+fn batch_ticks_like_individual_layers() {
+    for input_encoding in [
+        InputEncoding::Clamped,
+        InputEncoding::Scaled {
+            scale: 0.5,
+            offset: 1.0,
+        },
+        InputEncoding::Additive,
+    ] {
+        let settings = LayerSettings::default().input_encoding(input_encoding);
+        let mut layers: Vec<ThinkingLayer> = (0..5)
+            .map(|_| {
+                ThinkingLayer::with_settings(2, 20, 3, ActivationFunction::Tanh, settings.clone())
+                    .unwrap()
+            })
+            .collect();
+        let mut batch = ThinkingLayerBatch::from_layers(&layers).unwrap();
+
+        for tick in 0..10 {
+            let inputs: Vec<f64> = (0..layers.len() * 2)
+                .map(|index| (index + tick) as f64 / 10.0)
+                .collect();
+
+            for (layer, input) in layers.iter_mut().zip(inputs.chunks(2)) {
+                layer.tick(Some(input));
+            }
+            batch.tick(Some(&inputs));
+        }
+
+        for (member, layer) in layers.iter().enumerate() {
+            assert_eq!(batch.output(member), layer.output());
+            assert_eq!(batch.layer(member).genome(), layer.genome());
+            assert_eq!(batch.layer(member).snapshot(), layer.snapshot());
+        }
+    }
+}
+
+#[test]
+fn batch_rejects_mixed_topologies() {
+    let layers: Vec<ThinkingLayer> = vec![
+        ThinkingLayer::new(2, 20, 3, ActivationFunction::Tanh).unwrap(),
+        ThinkingLayer::new(2, 21, 3, ActivationFunction::Tanh).unwrap(),
+    ];
+
+    assert!(ThinkingLayerBatch::from_layers(&layers).is_err());
+    assert!(ThinkingLayerBatch::<f64>::from_layers(&[]).is_err());
+}
+
+#[test]
+fn samples_share_weights_but_keep_their_own_states() {
+    let settings = LayerSettings::default()
+        .connectivity(Connectivity::Random { fan_in: 4 })
+        .input_encoding(InputEncoding::Additive)
+        .update_mode(UpdateMode::Continuous {
+            integrator: Integrator::Euler,
+            dt: 0.25,
+        });
+    // Random connections are drawn per layer, relatives of one layer keep them.
+    let base: ThinkingLayer =
+        ThinkingLayer::with_settings(2, 20, 3, ActivationFunction::Tanh, settings).unwrap();
+    let mut rng = StdRng::seed_from_u64(3);
+    let layers: Vec<_> = (0..3)
+        .map(|_| {
+            let mut layer = base.clone();
+            layer.mutate(&Mutation::gaussian(1.0, 0.1).unwrap(), &mut rng);
+            layer
+        })
+        .collect();
+    let mut members: Vec<_> = layers
+        .iter()
+        .flat_map(|layer| [layer.clone(), layer.clone()])
+        .collect();
+    let mut batch = ThinkingLayerBatch::with_samples(&layers, 2).unwrap();
+    assert_eq!(
+        (batch.len(), batch.genome_count(), batch.samples()),
+        (6, 3, 2)
+    );
+
+    for tick in 0..10 {
+        let inputs: Vec<f64> = (0..members.len() * 2)
+            .map(|index| (index * tick) as f64 / 20.0)
+            .collect();
+
+        for (member, input) in members.iter_mut().zip(inputs.chunks(2)) {
+            member.tick_for(Some(input), 0.6);
+        }
+        batch.tick_for(Some(&inputs), 0.6);
+    }
+
+    for (index, member) in members.iter().enumerate() {
+        assert_eq!(batch.output(index), member.output());
+        assert_eq!(batch.layer(index).snapshot(), member.snapshot());
+    }
+    assert_ne!(batch.output(0), batch.output(1));
+}
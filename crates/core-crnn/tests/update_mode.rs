@@ -0,0 +1,129 @@
+use core_crnn::activation_function::ActivationFunction;
+use core_crnn::genome::GeneKind;
+use core_crnn::layer_settings::{Integrator, LayerSettings, UpdateMode};
+use core_crnn::thinking_layer::ThinkingLayer;
+
+fn layer_with_delays(update_mode: UpdateMode, delay: f64) -> ThinkingLayer {
+    let mut layer = ThinkingLayer::with_settings(
+        2,
+        12,
+        2,
+        ActivationFunction::Tanh,
+        LayerSettings::default().update_mode(update_mode),
+    )
+    .unwrap();
+    set_delays(&mut layer, delay);
+    layer
+}
+
+fn set_delays(layer: &mut ThinkingLayer, delay: f64) {
+    let genome = layer
+        .classified_genes()
+        .map(|(kind, gene)| {
+            if kind == GeneKind::Delay {
+                delay
+            } else {
+                *gene
+            }
+        })
+        .collect();
+    layer.set_genome(genome).unwrap();
+}
+
+/// Same genome as `layer`, updated according to `update_mode`.
+fn with_update_mode(layer: &ThinkingLayer, update_mode: UpdateMode) -> ThinkingLayer {
+    let mut copy = layer_with_delays(update_mode, 1.0);
+    copy.set_genome(layer.genome()).unwrap();
+    copy
+}
+
+fn outputs_after(mut layer: ThinkingLayer, ticks: usize) -> Vec<f64> {
+    for _ in 0..ticks {
+        layer.tick(Some(&[1.0, -0.5]));
+    }
+    layer.output()
+}
+
+#[test]
+fn leaky_integrator_with_unit_delays_matches_gated() {
+    let gated = layer_with_delays(UpdateMode::Gated, 1.0);
+    let leaky = with_update_mode(&gated, UpdateMode::LeakyIntegrator);
+
+    assert_eq!(outputs_after(gated, 10), outputs_after(leaky, 10));
+}
+
+#[test]
+fn leaky_integrator_responds_to_small_delay_changes() {
+    let gated_a = layer_with_delays(UpdateMode::Gated, 2.2);
+    let mut gated_b = gated_a.clone();
+    let leaky_a = with_update_mode(&gated_a, UpdateMode::LeakyIntegrator);
+
+    set_delays(&mut gated_b, 2.3);
+    let mut leaky_b = leaky_a.clone();
+    set_delays(&mut leaky_b, 2.3);
+
+    // Both delays round to two ticks, only the leaky integrator notices the difference.
+    assert_eq!(outputs_after(gated_a, 10), outputs_after(gated_b, 10));
+    assert_ne!(outputs_after(leaky_a, 10), outputs_after(leaky_b, 10));
+}
+
+fn continuous(integrator: Integrator, dt: f64) -> UpdateMode {
+    UpdateMode::Continuous { integrator, dt }
+}
+
+#[test]
+fn unit_euler_steps_match_leaky_integrator() {
+    let leaky = layer_with_delays(UpdateMode::LeakyIntegrator, 2.5);
+    let euler = with_update_mode(&leaky, continuous(Integrator::Euler, 1.0));
+
+    assert_eq!(outputs_after(leaky, 10), outputs_after(euler, 10));
+}
+
+#[test]
+fn integrators_converge_for_small_time_steps() {
+    let reference = layer_with_delays(continuous(Integrator::RungeKutta4, 0.001), 2.5);
+    let outputs_for = |update_mode| {
+        let mut layer = with_update_mode(&reference, update_mode);
+        layer.tick_for(Some(&[1.0, -0.5]), 3.0);
+        layer.output()
+    };
+
+    let expected = outputs_for(continuous(Integrator::RungeKutta4, 0.001));
+    for (update_mode, tolerance) in [
+        (continuous(Integrator::RungeKutta4, 0.1), 1e-6),
+        (continuous(Integrator::Euler, 0.001), 1e-3),
+    ] {
+        for (output, expected) in outputs_for(update_mode).iter().zip(&expected) {
+            assert!((output - expected).abs() < tolerance, "{:?}", update_mode);
+        }
+    }
+}
+
+#[test]
+fn continuous_behaviour_does_not_depend_on_think_steps() {
+    let mut once = layer_with_delays(continuous(Integrator::RungeKutta4, 0.125), 2.5);
+    let mut in_quarters = once.clone();
+
+    once.tick_for(Some(&[1.0, -0.5]), 1.0);
+    for _ in 0..4 {
+        in_quarters.tick_for(Some(&[1.0, -0.5]), 0.25);
+    }
+
+    assert_eq!(once.output(), in_quarters.output());
+}
+
+#[test]
+fn zero_delays_stay_finite() {
+    for integrator in [Integrator::Euler, Integrator::RungeKutta4] {
+        let mut layer = layer_with_delays(continuous(integrator, 1e-4), 0.0);
+        layer.tick_for(Some(&[1.0, -0.5]), 1e-4);
+        assert!(layer.output().iter().all(|value| value.is_finite()));
+    }
+}
+
+#[test]
+#[should_panic(expected = "Duration must be positive and finite, got 0")]
+fn zero_durations_are_rejected() {
+    let mut layer = layer_with_delays(continuous(Integrator::Euler, 0.1), 2.5);
+    layer.tick_for(None, 0.0);
+}
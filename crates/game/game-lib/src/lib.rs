@@ -1,37 +1,88 @@
-use core_crnn::thinking_layer::ThinkingLayer;
+use core_crnn::float::Float;
+use core_crnn::network::{Network, NetworkBatch};
 use std::time::Duration;
 
-pub trait GameMetaData{
-    fn from_model(model: ThinkingLayer) -> Self;
+pub trait GameMetaData {
+    /// Starts a game controlled by `model`, games with the same seed play out the same for the
+    /// same model. Models computing in another float type than the game are converted.
+    fn from_model<F: Float>(model: impl Network<F> + Send + 'static, seed: u64) -> Self;
+    /// Starts a game whose model runs outside of it, fed through [`Game::write_model_input`] and
+    /// [`Game::set_model_output`] by [`run_batch`].
+    fn with_external_model(seed: u64) -> Self;
     fn input_nodes() -> usize;
     fn output_nodes() -> usize;
 }
 
+/// A game played in `f32`, models are driven through [`Network<f32>`].
 pub trait Game {
-    fn extract_model(self) -> Option<ThinkingLayer>;
-    
+    fn extract_model(self) -> Option<Box<dyn Network<f32> + Send>>;
+
     fn run(&mut self, game_settings: GameSettings) -> f32 {
-        let tick_count =
-            game_settings.duration.as_millis() / game_settings.tick_duration.as_millis();
+        game_settings.for_each_step(|step| match step {
+            Step::Think(duration) => self.tick_model(duration),
+            Step::Tick(delta_time) => self.tick(delta_time),
+        });
+
+        self.score()
+    }
 
-        for _ in 0..game_settings.pre_ticks {
-            self.tick_model()
+    fn tick(&mut self, delta_time: Duration);
+    /// Ticks the models once, continuous-time models advance by `duration` units of model time
+    /// (see [`Network::tick_for`]).
+    fn tick_model(&mut self, duration: f64);
+    fn score(&self) -> f32;
+
+    /// Appends the inputs of an external model for its next tick to `out`, see
+    /// [`GameMetaData::with_external_model`].
+    fn write_model_input(&self, out: &mut Vec<f32>);
+    fn set_model_output(&mut self, output: &[f32]);
+}
+
+/// Runs `games` with external models side by side, member `i` of `models` plays game `i`. Plays
+/// out like [`Game::run`] with each member as the game's model and returns the scores.
+pub fn run_batch<G: Game, F: Float>(
+    games: &mut [G],
+    models: &mut dyn NetworkBatch<F>,
+    game_settings: GameSettings,
+) -> Vec<f32> {
+    assert_eq!(
+        games.len(),
+        models.len(),
+        "Every game needs exactly one model"
+    );
+
+    let (mut game_inputs, mut inputs, mut output) = (Vec::new(), Vec::new(), Vec::new());
+    let mut hand_out = |games: &mut [G], models: &dyn NetworkBatch<F>| {
+        for (member, game) in games.iter_mut().enumerate() {
+            output.clear();
+            output.extend(models.output(member).iter().copied().map(F::cast::<f32>));
+            game.set_model_output(&output);
         }
+    };
 
-        for _ in 0..tick_count {
-            for _ in 0..game_settings.think_steps {
-                self.tick_model()
+    hand_out(games, models);
+    game_settings.for_each_step(|step| match step {
+        Step::Think(duration) => {
+            game_inputs.clear();
+            for game in games.iter() {
+                game.write_model_input(&mut game_inputs);
             }
-
-            self.tick(game_settings.tick_duration)
+            inputs.clear();
+            inputs.extend(game_inputs.iter().copied().map(f32::cast::<F>));
+            models.tick_for(Some(&inputs), duration);
+            hand_out(games, models);
         }
+        Step::Tick(delta_time) => games.iter_mut().for_each(|game| game.tick(delta_time)),
+    });
 
-        self.score()
-    }
+    games.iter().map(Game::score).collect()
+}
 
-    fn tick(&mut self, delta_time: Duration);
-    fn tick_model(&mut self);
-    fn score(&self) -> f32;
+enum Step {
+    /// Ticks the models for the given model time.
+    Think(f64),
+    /// Advances the game by the given real time.
+    Tick(Duration),
 }
 
 pub struct GameSettings {
@@ -39,6 +90,7 @@ pub struct GameSettings {
     pre_ticks: usize,
     tick_duration: Duration,
     think_steps: usize,
+    think_duration: f64,
 }
 
 impl Default for GameSettings {
@@ -48,11 +100,30 @@ impl Default for GameSettings {
             pre_ticks: 0,
             tick_duration: Duration::from_secs_f32(1. / 60.), // 60 fps
             think_steps: 1,
+            think_duration: 1.,
         }
     }
 }
 
 impl GameSettings {
+    fn for_each_step(&self, mut step: impl FnMut(Step)) {
+        let tick_count = self.duration.as_millis() / self.tick_duration.as_millis();
+
+        let think_step_duration = self.think_duration / self.think_steps.max(1) as f64;
+
+        for _ in 0..self.pre_ticks {
+            step(Step::Think(think_step_duration))
+        }
+
+        for _ in 0..tick_count {
+            for _ in 0..self.think_steps {
+                step(Step::Think(think_step_duration))
+            }
+
+            step(Step::Tick(self.tick_duration))
+        }
+    }
+
     pub fn duration(mut self, duration: Duration) -> Self {
         self.duration = duration;
         self
@@ -72,4 +143,18 @@ impl GameSettings {
         self.think_steps = think_steps;
         self
     }
+
+    /// Model time that passes per game tick, split evenly across the think steps. Only
+    /// continuous-time models use it, so their behaviour does not depend on `think_steps`.
+    ///
+    /// Panics if `think_duration` is not positive and finite.
+    pub fn think_duration(mut self, think_duration: f64) -> Self {
+        assert!(
+            think_duration.is_finite() && think_duration > 0.0,
+            "Think duration must be positive and finite, got {}",
+            think_duration
+        );
+        self.think_duration = think_duration;
+        self
+    }
 }
@@ -1,6 +1,9 @@
+use core_crnn::float::Float;
+use core_crnn::network::{self, Network};
 use game_lib::{Game, GameMetaData};
 use ggez::glam::{vec2, Vec2};
-use rand::random_range;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use std::f32::consts::FRAC_PI_4;
 use std::time::Duration;
 
@@ -26,6 +29,8 @@ pub struct PongGame {
     pub player: (PongPlayer, PongPlayer),
     pub state: PongGameState,
     score: f32,
+    /// Source of the ball directions, seeded games always play out the same.
+    rng: StdRng,
 }
 
 pub struct PongGameState {
@@ -34,8 +39,8 @@ pub struct PongGameState {
     pub ball_dir: Vec2,
 }
 
-pub fn random_ball_direction(direction: Direction) -> Vec2 {
-    let angle = random_range(-FRAC_PI_4..FRAC_PI_4);
+pub fn random_ball_direction<R: Rng + ?Sized>(direction: Direction, rng: &mut R) -> Vec2 {
+    let angle = rng.random_range(-FRAC_PI_4..FRAC_PI_4);
     let mut dir = Vec2::from_angle(angle);
     direction.orient_vec2(&mut dir);
     dir
@@ -43,7 +48,15 @@ pub fn random_ball_direction(direction: Direction) -> Vec2 {
 
 impl PongGame {
     pub fn new(player_one: PongPlayer, player_two: PongPlayer) -> Self {
-        let ball_dir = random_ball_direction(Direction::Left);
+        Self::with_rng(player_one, player_two, StdRng::from_os_rng())
+    }
+
+    pub fn with_seed(player_one: PongPlayer, player_two: PongPlayer, seed: u64) -> Self {
+        Self::with_rng(player_one, player_two, StdRng::seed_from_u64(seed))
+    }
+
+    fn with_rng(player_one: PongPlayer, player_two: PongPlayer, mut rng: StdRng) -> Self {
+        let ball_dir = random_ball_direction(Direction::Left, &mut rng);
 
         PongGame {
             player: (player_one, player_two),
@@ -53,19 +66,25 @@ impl PongGame {
                 ball_dir,
             },
             score: 0.0,
+            rng,
         }
     }
 
     fn reset_ball(&mut self, direction: Direction) {
         self.state.ball_pos = vec2(0.5, 0.5);
 
-        self.state.ball_dir = random_ball_direction(direction);
+        self.state.ball_dir = random_ball_direction(direction, &mut self.rng);
     }
 }
 
 impl GameMetaData for PongGame {
-    fn from_model(model: core_crnn::thinking_layer::ThinkingLayer) -> Self {
-        PongGame::new(PongPlayer::model(model), PongPlayer::sync())
+    fn from_model<F: Float>(mut model: impl Network<F> + Send + 'static, seed: u64) -> Self {
+        // Every game is a new episode, nothing carries over from earlier ones.
+        model.reset_state();
+        PongGame::with_seed(PongPlayer::model(model), PongPlayer::sync(), seed)
+    }
+    fn with_external_model(seed: u64) -> Self {
+        PongGame::with_seed(PongPlayer::external(), PongPlayer::sync(), seed)
     }
     fn input_nodes() -> usize {
         5
@@ -77,7 +96,7 @@ impl GameMetaData for PongGame {
 }
 
 impl Game for PongGame {
-    fn extract_model(self) -> Option<core_crnn::thinking_layer::ThinkingLayer> {
+    fn extract_model(self) -> Option<Box<dyn Network<f32> + Send>> {
         match self.player.0.input {
             PongPlayerInput::Model(model) => Some(model),
             _ => None,
@@ -133,35 +152,39 @@ impl Game for PongGame {
         }
     }
 
-    fn tick_model(&mut self) {
-        if let PongPlayerInput::Model(model) = &mut self.player.0.input {
-            let input = vec![
-                self.player.0.pos as f64,
-                self.state.ball_pos.x as f64,
-                self.state.ball_pos.y as f64,
-                self.state.ball_dir.x as f64,
-                self.state.ball_dir.y as f64,
-            ];
-
-            model.tick(Some(input));
-        }
-
-        if let PongPlayerInput::Model(model) = &mut self.player.1.input {
-            let input = vec![
-                self.player.1.pos as f64,
-                self.state.ball_pos.x as f64,
-                self.state.ball_pos.y as f64,
-                self.state.ball_dir.x as f64,
-                self.state.ball_dir.y as f64,
-            ];
-
-            model.tick(Some(input));
+    fn tick_model(&mut self, duration: f64) {
+        for player in [&mut self.player.0, &mut self.player.1] {
+            let input = model_input(player.pos, &self.state);
+            if let PongPlayerInput::Model(model) = &mut player.input {
+                model.tick_for(Some(&input), duration);
+            }
         }
     }
 
     fn score(&self) -> f32 {
         self.score
     }
+
+    fn write_model_input(&self, out: &mut Vec<f32>) {
+        out.extend_from_slice(&model_input(self.player.0.pos, &self.state));
+    }
+
+    fn set_model_output(&mut self, output: &[f32]) {
+        if let PongPlayerInput::External(last_output) = &mut self.player.0.input {
+            last_output.clear();
+            last_output.extend_from_slice(output);
+        }
+    }
+}
+
+fn model_input(player_pos: f32, state: &PongGameState) -> [f32; 5] {
+    [
+        player_pos,
+        state.ball_pos.x,
+        state.ball_pos.y,
+        state.ball_dir.x,
+        state.ball_dir.y,
+    ]
 }
 
 pub struct PongPlayer {
@@ -187,9 +210,18 @@ impl PongPlayer {
         }
     }
 
-    pub fn model(model: core_crnn::thinking_layer::ThinkingLayer) -> PongPlayer {
+    /// Player driven by `model`, which is converted to `f32` if it computes in another type.
+    pub fn model<F: Float>(model: impl Network<F> + Send + 'static) -> PongPlayer {
+        PongPlayer {
+            input: PongPlayerInput::Model(network::boxed(model)),
+            pos: 0.5,
+        }
+    }
+
+    /// Player driven by a model outside of the game, see [`GameMetaData::with_external_model`].
+    pub fn external() -> PongPlayer {
         PongPlayer {
-            input: PongPlayerInput::Model(model),
+            input: PongPlayerInput::External(vec![0.0; PongGame::output_nodes()]),
             pos: 0.5,
         }
     }
@@ -224,7 +256,9 @@ pub enum PongPlayerInput {
         down_pressed: bool,
     },
     Sync,
-    Model(core_crnn::thinking_layer::ThinkingLayer),
+    Model(Box<dyn Network<f32> + Send>),
+    /// Last output of a model run outside of the game.
+    External(Vec<f32>),
 }
 
 impl PongPlayerInput {
@@ -243,7 +277,8 @@ impl PongPlayerInput {
                 _ => 0.0,
             },
             PongPlayerInput::Sync => (state.ball_pos.y - (player_pos + PLAYER_HEIGHT / 2.0)) * 5.,
-            PongPlayerInput::Model(model) => *model.output().first().unwrap() as f32,
+            PongPlayerInput::Model(model) => *model.output().first().unwrap(),
+            PongPlayerInput::External(output) => *output.first().unwrap(),
         }
     }
 }
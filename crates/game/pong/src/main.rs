@@ -15,7 +15,8 @@ fn main() -> GameResult {
         .window_mode(ggez::conf::WindowMode::default().dimensions(720.0, 720.0))
         .build()?;
 
-    let model = ThinkingLayer::new(
+    // Pong plays in f32, so the model computes in it as well.
+    let model = ThinkingLayer::<f32>::new(
         PongGame::input_nodes(),
         100,
         PongGame::output_nodes(),
@@ -84,7 +84,7 @@ impl Pong {
 
 impl event::EventHandler<GameError> for Pong {
     fn update(&mut self, ctx: &mut Context) -> GameResult {
-        self.game.tick_model();
+        self.game.tick_model(1.);
         self.game.tick(ctx.time.delta());
         Ok(())
     }
@@ -1,2 +1 @@
 pub mod model_trainer;
-pub mod persisted_genome;
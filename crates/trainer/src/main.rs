@@ -1,31 +1,59 @@
 mod model_trainer;
 
-use crate::model_trainer::{ModelTrainer, TrainConfig};
+use crate::model_trainer::{stream_seed, ModelTrainer, TrainConfig, BASE_MODEL_STREAM};
 use core_crnn::activation_function::ActivationFunction::Tanh;
-use core_crnn::genome::Genome;
+use core_crnn::crossover::Crossover;
+use core_crnn::layer_settings::LayerSettings;
+use core_crnn::mutation::Mutation;
+use core_crnn::persisted_model::{ModelMetadata, PersistedModel};
 use core_crnn::thinking_layer::ThinkingLayer;
 use game_lib::GameMetaData;
 use ggez::event;
 use pong::game::{PongGame, PongPlayer};
 use pong::pong::Pong;
+use rand::rngs::StdRng;
+use rand::{random, SeedableRng};
 use std::fs;
-use trainer::persisted_genome::PersistedGenome;
+use std::path::Path;
 
 fn main() {
+    // Set SEED to reproduce an earlier run.
+    let seed = std::env::var("SEED")
+        .ok()
+        .and_then(|seed| seed.parse().ok())
+        .unwrap_or_else(random);
+    println!("Seed: {}", seed);
+
+    let internal_size = PongGame::input_nodes() + 5 + PongGame::output_nodes();
     let mut last_saved = None;
-    let mut model = ThinkingLayer::new(
-        PongGame::input_nodes(),
-        PongGame::input_nodes() + 5 + PongGame::output_nodes(),
-        PongGame::output_nodes(),
-        Tanh,
-    )
-    .unwrap();
-    if let Ok(bytes) = fs::read("model.json") {
+    let model: ThinkingLayer = if Path::new("model.json").exists() {
         println!("Loading model...");
-        let data: PersistedGenome = serde_json::from_slice(&bytes).unwrap();
-        model.load_genome(data.genome);
-        last_saved = Some(data.score);
-    }
+        let data = PersistedModel::load("model.json")
+            .or_else(|error| {
+                // Older trainers only saved the score and genome of the pong layer.
+                PersistedModel::from_legacy_json(
+                    &fs::read("model.json")?,
+                    PongGame::input_nodes(),
+                    internal_size,
+                    PongGame::output_nodes(),
+                    Tanh,
+                )
+                .map_err(|_| error)
+            })
+            .unwrap_or_else(|error| panic!("Cannot load model.json: {:#}", error));
+        last_saved = data.metadata.score;
+        data.into_layer().unwrap()
+    } else {
+        ThinkingLayer::with_rng(
+            PongGame::input_nodes(),
+            internal_size,
+            PongGame::output_nodes(),
+            Tanh,
+            LayerSettings::default(),
+            &mut StdRng::seed_from_u64(stream_seed(seed, &[BASE_MODEL_STREAM])),
+        )
+        .unwrap()
+    };
 
     let mut trainer = ModelTrainer::new(
         model,
@@ -33,8 +61,9 @@ fn main() {
             epoch_size: 500,
             sample_size: 10,
             survival_rate: 0.1,
-            mutation_probability: 0.05,
-            mutation_strength: 0.2,
+            mutation: Mutation::gaussian(0.05, 0.2).unwrap(),
+            crossover: Crossover::Blend,
+            seed,
         },
     );
 
@@ -43,22 +72,20 @@ fn main() {
         let all_time_best = trainer.overall_best().as_ref().unwrap().score;
 
         if last_saved.is_none() || last_saved.unwrap() < all_time_best {
-            let best_genome = trainer
-                .overall_best()
-                .as_ref()
-                .unwrap()
-                .model
-                .genome()
-                .to_vec();
+            let best_model = &trainer.overall_best().as_ref().unwrap().model;
 
             println!("Saving new best model...");
             last_saved = Some(all_time_best);
-            let json = serde_json::to_string(&PersistedGenome {
-                score: all_time_best,
-                genome: best_genome,
-            })
+            PersistedModel::from_layer(
+                best_model,
+                ModelMetadata::default()
+                    .score(all_time_best)
+                    .generation(generation_index)
+                    .created_now(),
+            )
+            .unwrap()
+            .save("model.json")
             .unwrap();
-            fs::write("model.json", json).unwrap();
         }
 
         println!(
@@ -1,46 +1,70 @@
+use core_crnn::crossover::Crossover;
+use core_crnn::float::Float;
 use core_crnn::genome::Genome;
+use core_crnn::mutation::Mutation;
+use core_crnn::network::Network;
 use core_crnn::thinking_layer::ThinkingLayer;
-use game_lib::{Game, GameMetaData, GameSettings};
+use game_lib::{run_batch, Game, GameMetaData, GameSettings};
 use rand::distr::weighted::WeightedIndex;
 use rand::distr::Distribution;
-use rand::rng;
-use rayon::iter::ParallelIterator;
-use rayon::iter::{IntoParallelIterator, ParallelDrainRange};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rayon::iter::ParallelDrainRange;
+use rayon::iter::{IndexedParallelIterator, ParallelIterator};
+use std::marker::PhantomData;
 use std::time::Duration;
 
-pub struct ModelTrainer {
-    generation: Vec<ThinkingLayer>,
+/// Evolves a population of models computing in `F`, single [`ThinkingLayer`]s by default or
+/// composites like [`StackedNetwork`](core_crnn::stacked_network::StackedNetwork).
+pub struct ModelTrainer<M = ThinkingLayer, F: Float = f64> {
+    generation: Vec<M>,
     config: TrainConfig,
-    overall_best: Option<TrainResult>,
-    last_generation_best: Option<TrainResult>,
+    overall_best: Option<TrainResult<M>>,
+    last_generation_best: Option<TrainResult<M>>,
+    /// Drives mutation, selection and crossover, which all happen on one thread.
+    rng: StdRng,
+    generation_index: u64,
+    float: PhantomData<F>,
 }
 
-pub struct TrainResult {
+pub struct TrainResult<M = ThinkingLayer> {
     pub score: f32,
-    pub model: ThinkingLayer,
+    pub model: M,
 }
 
 pub struct TrainConfig {
     pub epoch_size: usize,
     pub sample_size: usize,
     pub survival_rate: f32,
-    pub mutation_probability: f64,
-    pub mutation_strength: f64,
+    /// Applied to every model of a new generation, see [`Mutation`].
+    pub mutation: Mutation,
+    /// How survivors are recombined into the rest of a new generation, see [`Crossover`].
+    pub crossover: Crossover,
+    /// Master seed of all randomness, runs with the same seed and config are identical.
+    pub seed: u64,
 }
 
-impl ModelTrainer {
-    pub fn new(base_model: ThinkingLayer, config: TrainConfig) -> Self {
+impl<M, F> ModelTrainer<M, F>
+where
+    M: Genome<Child = M> + Network<F> + Clone + Send + Sync + 'static,
+    F: Float,
+{
+    pub fn new(base_model: M, config: TrainConfig) -> Self {
+        let mut rng = StdRng::seed_from_u64(stream_seed(config.seed, &[EVOLUTION_STREAM]));
         Self {
             generation: (0..config.epoch_size)
                 .map(|_| {
                     let mut relative = base_model.clone();
-                    relative.mutate(config.mutation_probability, config.mutation_strength);
+                    relative.mutate(&config.mutation, &mut rng);
                     relative
                 })
                 .collect(),
             config,
             overall_best: None,
             last_generation_best: None,
+            rng,
+            generation_index: 0,
+            float: PhantomData,
         }
     }
 
@@ -48,18 +72,36 @@ impl ModelTrainer {
         let mut model_scores: Vec<_> = self
             .generation
             .par_drain(..)
-            .map(|model| {
-                let avg_score = (0..self.config.sample_size)
-                    .into_par_iter()
-                    .map(|_| {
-                        let mut game = TrainGame::from_model(model.clone());
-                        game.run(GameSettings::default().duration(Duration::from_secs(30)));
-                        game.score() / self.config.sample_size as f32
+            .enumerate()
+            .map(|(model_index, model)| {
+                // Every sample plays with its own seed and the scores are summed in order, so
+                // the result does not depend on how rayon schedules the models. The samples
+                // share the model's weights and tick together, see `Network::samples`.
+                let mut games: Vec<_> = (0..self.config.sample_size)
+                    .map(|sample_index| {
+                        TrainGame::with_external_model(stream_seed(
+                            self.config.seed,
+                            &[
+                                GAME_STREAM,
+                                self.generation_index,
+                                model_index as u64,
+                                sample_index as u64,
+                            ],
+                        ))
                     })
-                    .sum::<f32>();
-                (avg_score, model)
+                    .collect();
+                let scores: Vec<_> = run_batch(
+                    &mut games,
+                    &mut *model.samples(self.config.sample_size),
+                    GameSettings::default().duration(Duration::from_secs(30)),
+                )
+                .into_iter()
+                .map(|score| score / self.config.sample_size as f32)
+                .collect();
+                (scores.iter().sum::<f32>(), model)
             })
             .collect();
+        self.generation_index += 1;
 
         model_scores.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
         let best_model = model_scores.remove(0);
@@ -91,7 +133,7 @@ impl ModelTrainer {
             model: best_model.1.clone(),
         });
 
-        let rng = &mut rng();
+        let rng = &mut self.rng;
         let mut survivors: Vec<_> = (0
             ..(self.config.epoch_size as f32 * self.config.survival_rate) as usize - 1)
             .map(|_| {
@@ -116,26 +158,51 @@ impl ModelTrainer {
                 let parent_a = &survivors[random_survivor_index.sample(rng)].1;
                 let parent_b = &survivors[random_survivor_index.sample(rng)].1;
 
-                ThinkingLayer::crossover(parent_a, parent_b, 1)
+                M::crossover(parent_a, parent_b, &self.config.crossover, 1, rng).unwrap_or_else(
+                    |error| {
+                        eprintln!("Crossover failed, keeping the parents: {}", error);
+                        vec![parent_a.clone(), parent_b.clone()]
+                    },
+                )
             })
             .collect();
         new_generation.extend(survivors.into_iter().map(|(_, survivor)| survivor));
 
         new_generation.iter_mut().for_each(|genome| {
-            genome.mutate(
-                self.config.mutation_probability,
-                self.config.mutation_strength,
-            );
+            genome.mutate(&self.config.mutation, rng);
         });
 
         self.generation = new_generation;
     }
 
-    pub fn overall_best(&self) -> &Option<TrainResult> {
+    pub fn overall_best(&self) -> &Option<TrainResult<M>> {
         &self.overall_best
     }
 
-    pub fn last_generation_best(&self) -> &Option<TrainResult> {
+    pub fn last_generation_best(&self) -> &Option<TrainResult<M>> {
         &self.last_generation_best
     }
 }
+
+/// Stream of the random base model a run starts from.
+pub const BASE_MODEL_STREAM: u64 = 0;
+/// Stream of mutation, selection and crossover.
+pub const EVOLUTION_STREAM: u64 = 1;
+/// Streams of the games, followed by the generation, model and sample index.
+pub const GAME_STREAM: u64 = 2;
+
+/// Seed of an independent random stream identified by `stream`, derived from the master seed
+/// with SplitMix64 steps. Every consumer of randomness starts its stream with one of the
+/// `*_STREAM` constants, so no two draw the same numbers.
+pub fn stream_seed(seed: u64, stream: &[u64]) -> u64 {
+    stream
+        .iter()
+        .fold(split_mix(seed), |state, value| split_mix(state ^ value))
+}
+
+fn split_mix(value: u64) -> u64 {
+    let mut value = value.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    value ^ (value >> 31)
+}